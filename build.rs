@@ -0,0 +1,40 @@
+//! Fingerprints the formatter's source so that caches written by a
+//! different build are discarded, even when the package version is the same.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let mut files = vec![PathBuf::from("Cargo.toml")];
+    collect_sources(Path::new("src"), &mut files);
+    files.sort();
+
+    // FNV-1a: stable across toolchains, unlike the standard library's hasher.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for file in &files {
+        let contents = fs::read(file).unwrap_or_default();
+        let path = file.to_string_lossy().replace('\\', "/");
+        for byte in path.bytes().chain([0]).chain(contents).chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    println!("cargo:rustc-env=YARS_BUILD_FINGERPRINT={hash:016x}");
+}
+
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_sources(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
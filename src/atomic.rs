@@ -0,0 +1,179 @@
+//! Crash-safe replacement of file contents.
+//!
+//! New contents go to a temporary file in the target's directory, are synced
+//! to disk, take over the original's permissions (and, on Unix, ownership
+//! where the process is allowed to set it) and are renamed over the target.
+//! A file that did not exist yet gets the usual mode for new files instead.
+//! A symlink is followed so the file it points at is replaced and the link
+//! itself survives. Readers therefore see either the old or the new contents,
+//! never a truncated file.
+//!
+//! A [`FileStamp`] taken when the file was read lets the replacement be
+//! refused if another process (an editor, a generator) changed the file in
+//! the meantime, instead of silently discarding that change.
+
+use crate::YamlFormatError;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use thiserror::Error;
+
+/// Extension appended to a file's name for its backup copy.
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// How [`write_atomic`] replaces a file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Keep the previous contents as `<file>.bak` next to the file.
+    pub backup: bool,
+}
+
+/// Size, modification time and content hash of a file as it was read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+    sha256: [u8; 32],
+}
+
+impl FileStamp {
+    fn of(file: &mut File) -> io::Result<(Vec<u8>, Self)> {
+        let metadata = file.metadata()?;
+        let mut contents = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut contents)?;
+        let stamp = FileStamp {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            sha256: Sha256::digest(&contents).into(),
+        };
+        Ok((contents, stamp))
+    }
+}
+
+/// Read `path` together with a stamp of what was read, to pass to
+/// [`write_atomic`] when writing the file back.
+pub fn read_stamped(path: &Path) -> io::Result<(Vec<u8>, FileStamp)> {
+    FileStamp::of(&mut File::open(path)?)
+}
+
+/// Why [`write_atomic`] left a file untouched.
+#[derive(Debug, Error)]
+pub enum WriteError {
+    /// The file no longer matches the stamp taken when it was read.
+    #[error("modified by another process since it was read")]
+    Modified,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl WriteError {
+    /// The error to report for `path`.
+    pub fn into_error(self, path: &Path) -> YamlFormatError {
+        match self {
+            WriteError::Modified => YamlFormatError::ConcurrentModification(path.display().to_string()),
+            WriteError::Io(err) => YamlFormatError::WriteFailure(path.display().to_string(), err.to_string()),
+        }
+    }
+}
+
+/// Atomically replace the contents of `path` (or create it). With an
+/// `expected` stamp, the file is left alone unless it still matches it.
+pub fn write_atomic(
+    path: &Path,
+    contents: &[u8],
+    options: &WriteOptions,
+    expected: Option<&FileStamp>,
+) -> Result<(), WriteError> {
+    let target = resolve_symlinks(path)?;
+    let existing = fs::metadata(&target).ok();
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let name = target.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let prefix = format!(".{name}.");
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(&prefix).suffix(".tmp");
+    #[cfg(unix)]
+    if existing.is_none() {
+        use std::os::unix::fs::PermissionsExt;
+        // Temporary files are private (0600). A new file gets the mode any
+        // other new file would: open(2) applies the umask to 0666.
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let mut staging = builder.tempfile_in(dir)?;
+    staging.write_all(contents)?;
+    if let Some(metadata) = &existing {
+        staging.as_file().set_permissions(metadata.permissions())?;
+        preserve_owner(staging.as_file(), metadata);
+    }
+    staging.as_file().sync_all()?;
+
+    // Checked as late as possible; only a write landing between here and
+    // the rename can still be lost.
+    if let Some(expected) = expected {
+        let current = match File::open(&target) {
+            Ok(mut file) => Some(FileStamp::of(&mut file)?.1),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        if current.as_ref() != Some(expected) {
+            return Err(WriteError::Modified);
+        }
+    }
+    if options.backup && existing.is_some() {
+        fs::copy(&target, backup_path(&target))?;
+    }
+    staging.persist(&target).map_err(|err| err.error)?;
+
+    // Make the rename itself durable; not every platform can sync a directory.
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Path of the backup [`write_atomic`] keeps for `path` (`<file>.bak`).
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(BACKUP_EXTENSION);
+    path.with_file_name(name)
+}
+
+/// Follow symlinks to the file that should be replaced. A dangling link
+/// resolves to the (not yet existing) file it names.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut current = path.to_path_buf();
+    // Bounded like the kernel's own loop detection.
+    for _ in 0..40 {
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link = fs::read_link(&current)?;
+                current = match current.parent() {
+                    Some(parent) => parent.join(link),
+                    None => link,
+                };
+            }
+            _ => return Ok(current),
+        }
+    }
+    Err(io::Error::other(format!(
+        "too many levels of symbolic links: {}",
+        path.display()
+    )))
+}
+
+#[cfg(unix)]
+fn preserve_owner(file: &File, metadata: &fs::Metadata) {
+    use std::os::unix::fs::MetadataExt;
+    // Only root (or the owner, for the group) may do this; otherwise the new
+    // file keeps the writer's ownership.
+    let _ = std::os::unix::fs::fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_file: &File, _metadata: &fs::Metadata) {}
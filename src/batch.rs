@@ -0,0 +1,193 @@
+//! Formatting files in place, with a result for every input.
+//!
+//! A [`Batch`] reads, formats and rewrites one file at a time, consulting a
+//! [`FormatCache`] when given one. Each call yields a [`FileResult`], so
+//! callers can report files as they complete; [`Batch::run`] collects them
+//! into a [`BatchReport`].
+
+use crate::atomic::{self, WriteOptions};
+use crate::cache::FormatCache;
+use crate::diff::{change_stats, ChangeStats};
+use crate::report::FileStatus;
+use crate::{encoding, format_yaml_string_with_options, FormatOptions, YamlFormatError};
+use std::path::{Path, PathBuf};
+
+type Formatter<'a> = dyn FnMut(&str, &FormatOptions) -> Result<String, YamlFormatError> + 'a;
+
+/// What happened to one input file.
+#[derive(Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    pub error: Option<YamlFormatError>,
+    /// How much formatting changed the file; empty unless it changed.
+    pub stats: ChangeStats,
+    /// The text that failed to format, for quoting the error's location.
+    pub source: Option<String>,
+}
+
+impl FileResult {
+    pub fn unchanged(path: &Path) -> FileResult {
+        FileResult {
+            path: path.to_path_buf(),
+            status: FileStatus::Unchanged,
+            error: None,
+            stats: ChangeStats::default(),
+            source: None,
+        }
+    }
+
+    pub fn changed(path: &Path, original: &str, formatted: &str) -> FileResult {
+        FileResult {
+            status: FileStatus::Changed,
+            stats: change_stats(original, formatted),
+            ..FileResult::unchanged(path)
+        }
+    }
+
+    /// `path` failed with `error`, raised while formatting `source` if given.
+    pub fn failed(path: &Path, error: YamlFormatError, source: Option<String>) -> FileResult {
+        FileResult {
+            status: FileStatus::Error,
+            error: Some(error),
+            source,
+            ..FileResult::unchanged(path)
+        }
+    }
+}
+
+/// The results of a [`Batch::run`], in input order.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub files: Vec<FileResult>,
+}
+
+impl BatchReport {
+    /// Number of files that were (or, when checking, would be) rewritten.
+    pub fn changed(&self) -> usize {
+        self.count(FileStatus::Changed)
+    }
+
+    /// Number of files that failed.
+    pub fn errors(&self) -> usize {
+        self.count(FileStatus::Error)
+    }
+
+    fn count(&self, status: FileStatus) -> usize {
+        self.files.iter().filter(|file| file.status == status).count()
+    }
+}
+
+/// Formats files in place, or only checks them.
+pub struct Batch<'a> {
+    check_only: bool,
+    write: WriteOptions,
+    cache: Option<&'a mut FormatCache>,
+    formatter: Box<Formatter<'a>>,
+}
+
+impl<'a> Batch<'a> {
+    /// A batch that rewrites files, or with `check_only` just reports which
+    /// would change.
+    pub fn new(check_only: bool) -> Batch<'a> {
+        Batch {
+            check_only,
+            write: WriteOptions::default(),
+            cache: None,
+            formatter: Box::new(format_yaml_string_with_options),
+        }
+    }
+
+    /// How rewritten files are replaced.
+    pub fn write_options(mut self, write: WriteOptions) -> Batch<'a> {
+        self.write = write;
+        self
+    }
+
+    /// Skip files `cache` records as formatted, and record those found or
+    /// left formatted.
+    pub fn cache(mut self, cache: &'a mut FormatCache) -> Batch<'a> {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Format text with `formatter` instead of
+    /// [`format_yaml_string_with_options`].
+    pub fn formatter(
+        mut self,
+        formatter: impl FnMut(&str, &FormatOptions) -> Result<String, YamlFormatError> + 'a,
+    ) -> Batch<'a> {
+        self.formatter = Box::new(formatter);
+        self
+    }
+
+    /// Format `text` the way [`Batch::format_file`] formats a file's contents.
+    pub fn format(&mut self, text: &str, options: &FormatOptions) -> Result<String, YamlFormatError> {
+        (self.formatter)(text, options)
+    }
+
+    /// Format every file in `paths` with `options`.
+    pub fn run<P>(mut self, paths: P, options: &FormatOptions) -> BatchReport
+    where
+        P: IntoIterator,
+        P::Item: AsRef<Path>,
+    {
+        BatchReport {
+            files: paths
+                .into_iter()
+                .map(|path| self.format_file(path.as_ref(), options))
+                .collect(),
+        }
+    }
+
+    /// Format `path` with `options`, rewriting it if it changes. The file is
+    /// left alone if it is modified while being formatted.
+    pub fn format_file(&mut self, path: &Path, options: &FormatOptions) -> FileResult {
+        if !path.exists() {
+            let error = YamlFormatError::MissingFile(path.display().to_string());
+            return FileResult::failed(path, error, None);
+        }
+        let (bytes, stamp) = match atomic::read_stamped(path) {
+            Ok(read) => read,
+            Err(err) => {
+                let error = YamlFormatError::ReadFailure(path.display().to_string(), err.to_string());
+                return FileResult::failed(path, error, None);
+            }
+        };
+        let decoded = match encoding::decode(&bytes, path) {
+            Ok(decoded) => decoded,
+            Err(error) => return FileResult::failed(path, error, None),
+        };
+        let original = &decoded.text;
+
+        if self
+            .cache
+            .as_deref()
+            .is_some_and(|cache| cache.is_formatted(path, original, options))
+        {
+            return FileResult::unchanged(path);
+        }
+
+        let formatted = match self.format(original, options) {
+            Ok(formatted) => formatted,
+            Err(error) => return FileResult::failed(path, error.with_path(path), Some(decoded.text)),
+        };
+
+        if *original == formatted && options.encoding.is_none_or(|target| target == decoded.encoding) {
+            if let Some(cache) = self.cache.as_deref_mut() {
+                cache.record_formatted(path, original, options);
+            }
+            return FileResult::unchanged(path);
+        }
+        if !self.check_only {
+            let contents = decoded.encode(&formatted, options.encoding);
+            if let Err(err) = atomic::write_atomic(path, &contents, &self.write, Some(&stamp)) {
+                return FileResult::failed(path, err.into_error(path), None);
+            }
+            if let Some(cache) = self.cache.as_deref_mut() {
+                cache.record_formatted(path, &formatted, options);
+            }
+        }
+        FileResult::changed(path, original, &formatted)
+    }
+}
@@ -1,19 +1,41 @@
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
+use std::collections::HashSet;
 use std::fs;
-use std::io;
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use yars_yaml_formatter::format_yaml_string;
+use yars_yaml_formatter::atomic::{read_stamped, write_atomic, WriteOptions};
+use yars_yaml_formatter::cache::{FormatCache, DEFAULT_CACHE_DIR};
+use yars_yaml_formatter::config::ConfigResolver;
+#[cfg(unix)]
+use yars_yaml_formatter::daemon;
+use yars_yaml_formatter::encoding::{self, Decoded};
+use yars_yaml_formatter::git::{self, Repository};
+use yars_yaml_formatter::lsp;
+use yars_yaml_formatter::merge::{merge_yaml_strings, MergeOutcome};
+use yars_yaml_formatter::watch;
+use yars_yaml_formatter::report::{
+    render_snippet, CheckstyleReporter, ErrorRecord, FileRecord, GithubReporter,
+    JsonLinesReporter, JsonReporter, JunitReporter, Reporter, SarifReporter, Summary, TextReporter,
+};
+use yars_yaml_formatter::{
+    format_yaml_string_with_options, Batch, ErrorCategory, FileResult, FormatOptions, YamlFormatError,
+};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "yars-format",
     version,
     about = "Format YAML files using the yars formatter",
-    long_about = None
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Run in check mode (report changes without writing)
     #[arg(long)]
     check: bool,
@@ -22,6 +44,66 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Report format for per-file results [default: github when GITHUB_ACTIONS=true, else text]
+    #[arg(long, visible_alias = "output-format", value_enum)]
+    reporter: Option<ReporterKind>,
+
+    /// Use this configuration file instead of discovering `.yars.toml` / `pyproject.toml`
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print the effective configuration for each FILE and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// Reformat every file instead of skipping ones cached as already formatted
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Directory holding the formatted-file cache
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_CACHE_DIR)]
+    cache_dir: PathBuf,
+
+    /// Format the staged content of YAML files in the git index and re-stage it;
+    /// FILE arguments, if any, restrict the selection
+    #[arg(long, conflicts_with = "since")]
+    staged: bool,
+
+    /// Only process YAML files changed relative to this git revision;
+    /// FILE arguments, if any, restrict the selection
+    #[arg(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Act as a git clean/smudge filter: read a blob on stdin and write its
+    /// canonical form to stdout. An optional FILE (git's `%f`) selects the
+    /// configuration; content that fails to format passes through unchanged
+    #[arg(long, conflicts_with_all = ["staged", "since", "check", "print_config"])]
+    filter: bool,
+
+    /// Keep running and reformat (or, with --check, re-check) YAML files as
+    /// they are saved; FILE arguments may be directories, watched recursively
+    #[arg(long, conflicts_with_all = ["staged", "since", "filter", "print_config"])]
+    watch: bool,
+
+    /// Keep each rewritten file's previous contents as `<file>.bak`
+    #[arg(long, conflicts_with = "check")]
+    backup: bool,
+
+    /// Exit with a code for the kind of error instead of 2: 3 for invalid
+    /// YAML, 4 for unsupported structure, 5 for file system errors and 6 for
+    /// configuration or environment failures. Mixed kinds still exit with 2
+    #[arg(long)]
+    detailed_exit_codes: bool,
+
+    /// Format through a running `yars-format daemon`, falling back to
+    /// in-process formatting when none is reachable
+    #[arg(long)]
+    use_daemon: bool,
+
+    /// Socket of the daemon used by --use-daemon
+    #[arg(long, value_name = "PATH", requires = "use_daemon")]
+    daemon_socket: Option<PathBuf>,
+
     /// Generate shell completion script for the given shell
     #[arg(long = "generate-completions", value_enum)]
     generate_completions: Option<Shell>,
@@ -29,15 +111,122 @@ struct Cli {
     /// YAML files to format
     #[arg(
         value_name = "FILE",
-        required_unless_present = "generate_completions",
+        required_unless_present_any = ["generate_completions", "staged", "since", "filter"],
         num_args = 1..
     )]
     files: Vec<PathBuf>,
 }
 
-struct FileOutcome {
-    changed: bool,
-    lines_changed: usize,
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Three-way structural merge for use as a git merge driver:
+    /// `yars-format merge-driver %O %A %B %P`
+    MergeDriver {
+        /// Common ancestor version (%O)
+        base: PathBuf,
+        /// Our version (%A); receives the merge result
+        ours: PathBuf,
+        /// Their version (%B)
+        theirs: PathBuf,
+        /// Path of the merged file in the repository (%P), used to find configuration
+        path: Option<PathBuf>,
+    },
+    /// Run a Language Server Protocol server on stdin/stdout for editor
+    /// format-on-save
+    Lsp,
+    /// Serve formatting requests on a Unix domain socket until a client
+    /// sends `shutdown` (see `--use-daemon`)
+    Daemon {
+        /// Socket to listen on [default: $XDG_RUNTIME_DIR/yars-format.sock]
+        #[arg(long, value_name = "PATH")]
+        socket: Option<PathBuf>,
+    },
+}
+
+/// The git index being formatted under `--staged`.
+struct StagedIndex {
+    repo: Repository,
+    /// Files with unstaged edits, whose working copies must not be rewritten.
+    unstaged: HashSet<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ReporterKind {
+    /// Human-readable summary
+    Text,
+    /// One JSON document with per-file records and a summary
+    Json,
+    /// One JSON object per line, streamed as files complete
+    JsonLines,
+    /// SARIF 2.1.0 for code-scanning tools
+    Sarif,
+    /// Checkstyle XML
+    Checkstyle,
+    /// JUnit XML test report
+    Junit,
+    /// GitHub Actions workflow-command annotations
+    Github,
+}
+
+/// Where documents are formatted: in this process, or by a daemon reached
+/// with `--use-daemon`.
+enum Engine {
+    InProcess,
+    #[cfg(unix)]
+    Daemon(daemon::Client),
+}
+
+impl Engine {
+    /// Connect to the daemon if asked to, falling back to in-process
+    /// formatting when none is running.
+    fn new(cli: &Cli) -> Self {
+        #[cfg(unix)]
+        if cli.use_daemon {
+            let socket = cli.daemon_socket.clone().unwrap_or_else(daemon::default_socket_path);
+            if let Ok(client) = daemon::Client::connect(&socket) {
+                return Engine::Daemon(client);
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = cli;
+        Engine::InProcess
+    }
+
+    fn format(&mut self, text: &str, options: &FormatOptions) -> Result<String, YamlFormatError> {
+        #[cfg(unix)]
+        if let Engine::Daemon(client) = self {
+            match client.format(text, options) {
+                Ok(result) => return result,
+                // The daemon went away mid-run; finish in-process.
+                Err(_) => *self = Engine::InProcess,
+            }
+        }
+        format_yaml_string_with_options(text, options)
+    }
+}
+
+fn file_record(result: FileResult) -> FileRecord {
+    let path = result.path.display().to_string();
+    let error = result.error.map(|err| {
+        let location = err.location();
+        ErrorRecord {
+            kind: err.kind().to_owned(),
+            code: err.code(),
+            message: err.to_string(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            snippet: location
+                .zip(result.source.as_deref())
+                .and_then(|((line, column), source)| render_snippet(&path, source, line, column)),
+        }
+    });
+    FileRecord {
+        lines_changed: result.stats.lines_changed,
+        first_changed_line: result.stats.first_changed_line,
+        path,
+        status: result.status,
+        error,
+    }
 }
 
 pub fn main() -> ExitCode {
@@ -50,107 +239,460 @@ pub fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
-    let mut changed_count = 0usize;
-    let mut error_count = 0usize;
-    let mut success_count = 0usize;
-
-    for path in &cli.files {
-        match process_file(path, cli.check) {
-            Ok(outcome) => {
-                success_count += 1;
-                if outcome.changed {
-                    changed_count += 1;
-                    if cli.verbose {
-                        if cli.check {
-                            println!(
-                                "{} - would reformat ({} differing line(s))",
-                                path.display(),
-                                outcome.lines_changed
-                            );
-                        } else {
-                            println!(
-                                "{} - reformatted ({} line(s) changed)",
-                                path.display(),
-                                outcome.lines_changed
-                            );
-                        }
-                    }
-                } else if cli.verbose {
-                    println!("{} - already formatted", path.display());
-                }
-            }
+    let mut resolver = match &cli.config {
+        Some(path) => match ConfigResolver::with_config_file(path) {
+            Ok(resolver) => resolver,
             Err(err) => {
-                error_count += 1;
                 eprintln!("Error: {}", err);
+                return ExitCode::from(2);
+            }
+        },
+        None => ConfigResolver::new(),
+    };
+
+    match &cli.command {
+        Some(Commands::MergeDriver {
+            base,
+            ours,
+            theirs,
+            path,
+        }) => return run_merge_driver(&mut resolver, base, ours, theirs, path.as_deref()),
+        Some(Commands::Lsp) => {
+            return match lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+                Ok(true) => ExitCode::SUCCESS,
+                Ok(false) => ExitCode::from(1),
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::from(2)
+                }
             }
         }
+        Some(Commands::Daemon { socket }) => return run_daemon(socket.as_deref()),
+        None => {}
+    }
+
+    if cli.print_config {
+        return print_config(&mut resolver, &cli.files);
+    }
+
+    if cli.filter {
+        return run_filter(&mut resolver, cli.files.first().map(PathBuf::as_path));
     }
 
-    if cli.check {
-        println!(
-            "Checked {} file(s); {} would change.",
-            success_count, changed_count
-        );
-    } else {
-        println!(
-            "Formatted {} file(s); {} updated, {} unchanged.",
-            success_count,
-            changed_count,
-            success_count.saturating_sub(changed_count)
-        );
+    let (files, staged) = match select_files(&cli) {
+        Ok(selection) => selection,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut cache = (!cli.no_cache).then(|| FormatCache::open(&cli.cache_dir));
+    let files = if cli.watch { watch::yaml_files(&files) } else { files };
+    let mut engine = Engine::new(&cli);
+    let code = process_batch(
+        &cli,
+        &mut resolver,
+        &mut engine,
+        &files,
+        staged.as_ref(),
+        cache.as_mut(),
+    );
+    if !cli.watch {
+        return code;
     }
+    run_watch(&cli, &mut resolver, &mut engine, &files, cache.as_mut())
+}
 
-    if error_count > 0 {
-        eprintln!("Encountered {} error(s).", error_count);
+/// Format (or check) `files`, reporting each result and a final summary.
+fn process_batch(
+    cli: &Cli,
+    resolver: &mut ConfigResolver,
+    engine: &mut Engine,
+    files: &[PathBuf],
+    staged: Option<&StagedIndex>,
+    mut cache: Option<&mut FormatCache>,
+) -> ExitCode {
+    let reporter_kind = cli.reporter.unwrap_or_else(|| {
+        if std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true") {
+            ReporterKind::Github
+        } else {
+            ReporterKind::Text
+        }
+    });
+    let mut reporter: Box<dyn Reporter> = match reporter_kind {
+        ReporterKind::Text => Box::new(TextReporter::new(
+            cli.verbose,
+            cli.check,
+            io::stdout(),
+            io::stderr(),
+        )),
+        ReporterKind::Json => Box::new(JsonReporter::new(io::stdout())),
+        ReporterKind::JsonLines => Box::new(JsonLinesReporter::new(io::stdout().lock())),
+        ReporterKind::Sarif => Box::new(SarifReporter::new(cli.check, io::stdout())),
+        ReporterKind::Checkstyle => Box::new(CheckstyleReporter::new(cli.check, io::stdout())),
+        ReporterKind::Junit => Box::new(JunitReporter::new(cli.check, io::stdout())),
+        ReporterKind::Github => Box::new(GithubReporter::new(
+            cli.verbose,
+            cli.check,
+            io::stdout(),
+            io::stderr(),
+        )),
+    };
+    let mut summary = Summary {
+        check: cli.check,
+        ..Summary::default()
+    };
+    let write = WriteOptions { backup: cli.backup };
+    let mut batch = Batch::new(cli.check)
+        .write_options(write.clone())
+        .formatter(|text, options| engine.format(text, options));
+    if let Some(cache) = cache.as_deref_mut() {
+        batch = batch.cache(cache);
+    }
+    let mut categories = HashSet::new();
+
+    for path in files {
+        let result = match resolver.resolve(path).and_then(|resolved| resolved.format_options()) {
+            Ok(options) => match staged {
+                Some(staged) => process_staged(path, staged, cli.check, &options, &write, &mut batch),
+                None => batch.format_file(path, &options),
+            },
+            Err(err) => FileResult::failed(path, err, None),
+        };
+        let record = file_record(result);
+        summary.add(&record);
+        if let Some(error) = &record.error {
+            categories.insert(error.code.category());
+        }
+        if let Err(err) = reporter.report_file(&record) {
+            eprintln!("Error: failed to write report: {}", err);
+            return ExitCode::from(2);
+        }
+    }
+
+    drop(batch);
+    if let Some(cache) = cache {
+        if let Err(err) = cache.save() {
+            eprintln!("Warning: {}", err);
+        }
+    }
+
+    if let Err(err) = reporter.finish(&summary) {
+        eprintln!("Error: failed to write report: {}", err);
         return ExitCode::from(2);
     }
 
-    if cli.check && changed_count > 0 {
+    if summary.errors > 0 {
+        return error_exit_code(cli.detailed_exit_codes, &categories);
+    }
+
+    if cli.check && summary.changed > 0 {
         return ExitCode::from(1);
     }
 
     ExitCode::SUCCESS
 }
 
-fn process_file(path: &Path, check_only: bool) -> Result<FileOutcome, String> {
-    let original = fs::read_to_string(path)
-        .map_err(|err| format!("{}: Failed to read file: {}", path.display(), err))?;
+/// Exit code for a run whose files failed with errors in `categories`.
+fn error_exit_code(detailed: bool, categories: &HashSet<ErrorCategory>) -> ExitCode {
+    let mut categories = categories.iter();
+    match (categories.next(), categories.next()) {
+        (Some(category), None) if detailed => ExitCode::from(match category {
+            ErrorCategory::Syntax => 3,
+            ErrorCategory::Structure => 4,
+            ErrorCategory::Io => 5,
+            ErrorCategory::Environment => 6,
+        }),
+        _ => ExitCode::from(2),
+    }
+}
 
-    let formatted =
-        format_yaml_string(&original).map_err(|err| format!("{}: {}", path.display(), err))?;
+/// Re-run [`process_batch`] on YAML files under `cli.files` as they are
+/// saved, until interrupted.
+fn run_watch(
+    cli: &Cli,
+    resolver: &mut ConfigResolver,
+    engine: &mut Engine,
+    initial: &[PathBuf],
+    mut cache: Option<&mut FormatCache>,
+) -> ExitCode {
+    let mut watcher = match watch::Watcher::new(&cli.files, watch::DEFAULT_DEBOUNCE) {
+        Ok(watcher) => watcher,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    for path in initial {
+        watcher.settle(path);
+    }
+    eprintln!("Watching for changes; press Ctrl-C to stop.");
 
-    let changed = original != formatted;
-    if changed {
-        if !check_only {
-            fs::write(path, &formatted)
-                .map_err(|err| format!("{}: Failed to write file: {}", path.display(), err))?;
+    loop {
+        let batch = match watcher.next_batch(None) {
+            Ok(batch) => batch,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::from(2);
+            }
+        };
+        // Configuration may have changed since the last batch.
+        let reloaded = match &cli.config {
+            Some(path) => ConfigResolver::with_config_file(path),
+            None => Ok(ConfigResolver::new()),
+        };
+        match reloaded {
+            Ok(reloaded) => {
+                *resolver = reloaded;
+                process_batch(cli, resolver, engine, &batch, None, cache.as_deref_mut());
+            }
+            // Skip the batch, but still settle it so the same saves do not
+            // fail again before the configuration is fixed.
+            Err(err) => eprintln!("Error: {}", err),
+        }
+        for path in &batch {
+            watcher.settle(path);
         }
-        let lines_changed = count_changed_lines(&original, &formatted);
-        Ok(FileOutcome {
-            changed: true,
-            lines_changed,
-        })
-    } else {
-        Ok(FileOutcome {
-            changed: false,
-            lines_changed: 0,
-        })
     }
 }
 
-fn count_changed_lines(original: &str, formatted: &str) -> usize {
-    let original_lines: Vec<&str> = original.lines().collect();
-    let formatted_lines: Vec<&str> = formatted.lines().collect();
-    let max_len = original_lines.len().max(formatted_lines.len());
+fn print_config(resolver: &mut ConfigResolver, files: &[PathBuf]) -> ExitCode {
+    let mut status = ExitCode::SUCCESS;
+    for path in files {
+        let options = resolver
+            .resolve(path)
+            .and_then(|resolved| Ok((resolved.format_options()?, resolved.sources)));
+        match options {
+            Ok((options, sources)) => {
+                println!("# {}", path.display());
+                for source in sources {
+                    println!("# from {}", source.display());
+                }
+                match toml::to_string(&options) {
+                    Ok(rendered) => print!("{}", rendered),
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        status = ExitCode::from(2);
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Error: {}: {}", path.display(), err);
+                status = ExitCode::from(2);
+            }
+        }
+    }
+    status
+}
 
-    let mut diff = 0usize;
-    for idx in 0..max_len {
-        let orig_line = original_lines.get(idx).copied().unwrap_or("");
-        let new_line = formatted_lines.get(idx).copied().unwrap_or("");
-        if orig_line != new_line {
-            diff += 1;
+#[cfg(unix)]
+fn run_daemon(socket: Option<&Path>) -> ExitCode {
+    let socket = socket.map_or_else(daemon::default_socket_path, Path::to_path_buf);
+    eprintln!("Listening on {}", socket.display());
+    match daemon::serve(&socket) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(2)
         }
     }
+}
+
+#[cfg(not(unix))]
+fn run_daemon(_socket: Option<&Path>) -> ExitCode {
+    eprintln!("Error: the formatting daemon requires Unix domain sockets");
+    ExitCode::from(2)
+}
 
-    diff
+/// Merge `base`/`ours`/`theirs` structurally into `ours`. When that conflicts,
+/// fall back to a textual merge of the formatted versions so the conflict
+/// markers only cover what genuinely disagrees. Exits 1 on conflict, as git
+/// expects from merge drivers.
+fn run_merge_driver(
+    resolver: &mut ConfigResolver,
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    path: Option<&Path>,
+) -> ExitCode {
+    let name = path.unwrap_or(ours);
+    let mut texts = Vec::with_capacity(3);
+    for file in [base, ours, theirs] {
+        let decoded = match fs::read(file) {
+            Ok(bytes) => encoding::decode(&bytes, file),
+            Err(err) => {
+                eprintln!("Error: {}: Failed to read file: {}", file.display(), err);
+                return ExitCode::from(2);
+            }
+        };
+        match decoded {
+            Ok(decoded) => texts.push(decoded),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    let options = match resolver.resolve(name).and_then(|resolved| resolved.format_options()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Error: {}: {}", name.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+
+    match merge_yaml_strings(&texts[0].text, &texts[1].text, &texts[2].text, &options) {
+        Ok(MergeOutcome::Merged(merged)) => {
+            return match write_merge_file(ours, &texts[1].encode(&merged, options.encoding)) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::from(2)
+                }
+            };
+        }
+        Ok(MergeOutcome::Conflicted(conflicts)) => {
+            for conflict in conflicts {
+                eprintln!("{}: {}", name.display(), conflict);
+            }
+        }
+        Err(err) => eprintln!("Warning: {}: {}", name.display(), err),
+    }
+
+    for (file, decoded) in [base, ours, theirs].into_iter().zip(&texts) {
+        let Ok(formatted) = format_yaml_string_with_options(&decoded.text, &options) else {
+            continue;
+        };
+        if let Err(err) = write_merge_file(file, &decoded.encode(&formatted, options.encoding)) {
+            eprintln!("Error: {}", err);
+            return ExitCode::from(2);
+        }
+    }
+    match git::merge_file(ours, base, theirs) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Replace one of the merge driver's files, which git reads back once the
+/// driver exits.
+fn write_merge_file(file: &Path, contents: &[u8]) -> Result<(), YamlFormatError> {
+    write_atomic(file, contents, &WriteOptions::default(), None).map_err(|err| err.into_error(file))
+}
+
+/// Format stdin to stdout for git's clean/smudge filters. Failures are
+/// reported on stderr but never block git: the blob passes through as-is.
+fn run_filter(resolver: &mut ConfigResolver, path: Option<&Path>) -> ExitCode {
+    let mut input = Vec::new();
+    if let Err(err) = io::stdin().read_to_end(&mut input) {
+        eprintln!("Error: Failed to read stdin: {}", err);
+        return ExitCode::from(2);
+    }
+
+    let formatted = encoding::decode(&input, path.unwrap_or(Path::new("<stdin>"))).and_then(|decoded| {
+        let options = resolver
+            .resolve(path.unwrap_or(Path::new(".")))?
+            .format_options()?;
+        let formatted = format_yaml_string_with_options(&decoded.text, &options)?;
+        Ok(decoded.encode(&formatted, options.encoding))
+    });
+    let output = match &formatted {
+        Ok(bytes) => bytes.as_slice(),
+        Err(err) => {
+            let name = path.map_or_else(|| "<stdin>".to_owned(), |path| path.display().to_string());
+            eprintln!("Warning: {}: {} (left unchanged)", name, err);
+            &input
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    if let Err(err) = stdout.write_all(output).and_then(|()| stdout.flush()) {
+        eprintln!("Error: Failed to write stdout: {}", err);
+        return ExitCode::from(2);
+    }
+    ExitCode::SUCCESS
+}
+
+/// The files to process and, under `--staged`, the index they come from.
+fn select_files(cli: &Cli) -> Result<(Vec<PathBuf>, Option<StagedIndex>), YamlFormatError> {
+    if !cli.staged && cli.since.is_none() {
+        return Ok((cli.files.clone(), None));
+    }
+
+    let cwd = std::env::current_dir().map_err(|err| YamlFormatError::Git(err.to_string()))?;
+    let repo = Repository::discover(&cwd)?;
+    if let Some(rev) = &cli.since {
+        return Ok((repo.changed_since(rev, &cli.files)?, None));
+    }
+
+    let files = repo.staged_files(&cli.files)?;
+    let unstaged = repo.unstaged_files()?;
+    Ok((files, Some(StagedIndex { repo, unstaged })))
+}
+
+/// Format the staged content of `path` and stage the result, also writing
+/// it to the working copy unless that has unstaged edits.
+fn process_staged(
+    path: &Path,
+    staged: &StagedIndex,
+    check_only: bool,
+    options: &FormatOptions,
+    write: &WriteOptions,
+    batch: &mut Batch,
+) -> FileResult {
+    let decoded = match staged.repo.read_staged(path) {
+        Ok(decoded) => decoded,
+        Err(err) => return FileResult::failed(path, err, None),
+    };
+    let original = &decoded.text;
+    let formatted = match batch.format(original, options) {
+        Ok(formatted) => formatted,
+        Err(err) => return FileResult::failed(path, err.with_path(path), Some(decoded.text)),
+    };
+    if *original == formatted && options.encoding.is_none_or(|target| target == decoded.encoding) {
+        return FileResult::unchanged(path);
+    }
+
+    if !check_only {
+        let contents = decoded.encode(&formatted, options.encoding);
+        if let Err(err) = stage(path, staged, &decoded, &contents, write) {
+            return FileResult::failed(path, err, None);
+        }
+    }
+    FileResult::changed(path, original, &formatted)
+}
+
+/// Stage `contents` for `path`, which was staged as `original`.
+fn stage(
+    path: &Path,
+    staged: &StagedIndex,
+    original: &Decoded,
+    contents: &[u8],
+    write: &WriteOptions,
+) -> Result<(), YamlFormatError> {
+    // Partially staged files keep their unstaged edits on disk; the
+    // formatted content only lands in the index.
+    if staged.unstaged.contains(path) {
+        return staged.repo.stage_contents(path, contents);
+    }
+    let (_, stamp) = read_stamped(path)
+        .map_err(|err| YamlFormatError::ReadFailure(path.display().to_string(), err.to_string()))?;
+    // Fully staged files match the index, unless edited since the staged
+    // set was computed. Git makes the comparison so that line-ending
+    // conversion and clean filters are taken into account.
+    if staged.repo.has_unstaged_changes(path)? {
+        return Err(YamlFormatError::ConcurrentModification(path.display().to_string()));
+    }
+    staged.repo.stage_contents(path, contents)?;
+    if let Err(err) = write_atomic(path, contents, write, Some(&stamp)) {
+        // Leave the index as it was rather than out of step with the file.
+        staged.repo.stage_contents(path, &original.encode(&original.text, None))?;
+        return Err(err.into_error(path));
+    }
+    Ok(())
 }
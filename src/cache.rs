@@ -0,0 +1,146 @@
+//! On-disk record of files already known to be formatted.
+//!
+//! An entry matches only while the file's size, modification time and SHA-256
+//! content hash are unchanged and it was checked with the same formatter
+//! version and effective [`FormatOptions`]. A cache written by another
+//! formatter version is discarded wholesale.
+
+use crate::{FormatOptions, YamlFormatError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Cache directory used when none is configured, relative to the working directory.
+pub const DEFAULT_CACHE_DIR: &str = ".yars_cache";
+
+/// Version stamped into cache files; a mismatch invalidates every entry.
+/// The package version alone would miss changes made between releases, so
+/// a fingerprint of the source it was built from is appended.
+pub const FORMATTER_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+", env!("YARS_BUILD_FINGERPRINT"));
+
+const CACHE_FILE_NAME: &str = "formatted.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+    sha256: String,
+    options: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CacheFile {
+    version: String,
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+#[derive(Serialize)]
+struct CacheFileRef<'a> {
+    version: &'a str,
+    entries: &'a HashMap<PathBuf, CacheEntry>,
+}
+
+/// Formatted-file cache backed by a JSON file inside a cache directory.
+#[derive(Debug)]
+pub struct FormatCache {
+    dir: PathBuf,
+    entries: HashMap<PathBuf, CacheEntry>,
+    dirty: bool,
+}
+
+impl FormatCache {
+    /// Load the cache stored in `dir`. Missing, unreadable or stale caches
+    /// start empty rather than failing.
+    pub fn open(dir: &Path) -> Self {
+        let entries = fs::read_to_string(dir.join(CACHE_FILE_NAME))
+            .ok()
+            .and_then(|text| serde_json::from_str::<CacheFile>(&text).ok())
+            .filter(|file| file.version == FORMATTER_VERSION)
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        Self {
+            dir: dir.to_path_buf(),
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Whether `path`, currently holding `contents`, is known to be formatted
+    /// under `options`.
+    pub fn is_formatted(&self, path: &Path, contents: &str, options: &FormatOptions) -> bool {
+        let Some(key) = cache_key(path) else {
+            return false;
+        };
+        let Some(entry) = self.entries.get(&key) else {
+            return false;
+        };
+        fingerprint(path, contents, options).as_ref() == Some(entry)
+    }
+
+    /// Remember that `path`, currently holding `contents`, is formatted under `options`.
+    pub fn record_formatted(&mut self, path: &Path, contents: &str, options: &FormatOptions) {
+        let (Some(key), Some(entry)) = (cache_key(path), fingerprint(path, contents, options)) else {
+            return;
+        };
+        if self.entries.get(&key) != Some(&entry) {
+            self.entries.insert(key, entry);
+            self.dirty = true;
+        }
+    }
+
+    /// Persist the cache if anything changed since it was opened.
+    pub fn save(&mut self) -> Result<(), YamlFormatError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let write_failure =
+            |path: &Path, err: std::io::Error| YamlFormatError::WriteFailure(path.display().to_string(), err.to_string());
+        fs::create_dir_all(&self.dir).map_err(|err| write_failure(&self.dir, err))?;
+        let ignore = self.dir.join(".gitignore");
+        if !ignore.exists() {
+            fs::write(&ignore, "*\n").map_err(|err| write_failure(&ignore, err))?;
+        }
+
+        let encoded = serde_json::to_string(&CacheFileRef {
+            version: FORMATTER_VERSION,
+            entries: &self.entries,
+        })
+        .map_err(|err| YamlFormatError::Format(err.to_string()))?;
+
+        // Write beside the target and rename so concurrent runs never see a torn file.
+        let target = self.dir.join(CACHE_FILE_NAME);
+        let staging = self.dir.join(format!("{CACHE_FILE_NAME}.{}", std::process::id()));
+        fs::write(&staging, encoded).map_err(|err| write_failure(&staging, err))?;
+        fs::rename(&staging, &target).map_err(|err| write_failure(&target, err))?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+fn cache_key(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+fn fingerprint(path: &Path, contents: &str, options: &FormatOptions) -> Option<CacheEntry> {
+    let metadata = fs::metadata(path).ok()?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let sha256 = Sha256::digest(contents.as_bytes())
+        .iter()
+        .fold(String::with_capacity(64), |mut hex, byte| {
+            use std::fmt::Write as _;
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+    Some(CacheEntry {
+        size: metadata.len(),
+        mtime_secs: mtime.as_secs(),
+        mtime_nanos: mtime.subsec_nanos(),
+        sha256,
+        options: serde_json::to_string(options).ok()?,
+    })
+}
@@ -0,0 +1,229 @@
+//! Project configuration discovery.
+//!
+//! Settings live in `.yars.toml` or in the `[tool.yars]` table of a
+//! `pyproject.toml`. Discovery walks up from the directory of each formatted
+//! file; settings closer to the file override those from parent directories,
+//! and a file with `root = true` stops the walk. Applicable `.editorconfig`
+//! properties fill in anything yars configuration leaves unset.
+
+use crate::editorconfig::EditorConfigResolver;
+use crate::encoding::Encoding;
+use crate::{DocumentMarkers, DuplicateKeys, FormatOptions, LineEnding, YamlFormatError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Dedicated configuration file name.
+pub const CONFIG_FILE_NAME: &str = ".yars.toml";
+
+/// Python project file consulted for a `[tool.yars]` table.
+pub const PYPROJECT_FILE_NAME: &str = "pyproject.toml";
+
+/// Settings read from a single configuration file. Unset fields inherit.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Do not consult configuration files in parent directories.
+    #[serde(skip_serializing_if = "is_false")]
+    pub root: bool,
+    /// Spaces added per nesting level.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent: Option<usize>,
+    /// Line terminator for formatted output.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_ending: Option<LineEnding>,
+    /// Whether formatted output ends with a line terminator.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_newline: Option<bool>,
+    /// Encoding formatted files are written in; unset keeps each file's own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<Encoding>,
+    /// Whether `---`/`...` markers and directives are written.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_markers: Option<DocumentMarkers>,
+    /// What to do with a key repeated within a mapping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_keys: Option<DuplicateKeys>,
+}
+
+impl Config {
+    /// Parse a configuration file. `pyproject.toml` files without a
+    /// `[tool.yars]` table yield `None`.
+    pub fn from_file(path: &Path) -> Result<Option<Config>, YamlFormatError> {
+        let text = fs::read_to_string(path).map_err(|err| {
+            YamlFormatError::ReadFailure(path.display().to_string(), err.to_string())
+        })?;
+        let invalid =
+            |err: toml::de::Error| YamlFormatError::InvalidConfig(path.display().to_string(), err.message().to_owned());
+
+        if path.file_name().is_some_and(|name| name == PYPROJECT_FILE_NAME) {
+            let mut table: toml::Table = toml::from_str(&text).map_err(invalid)?;
+            let section = table
+                .remove("tool")
+                .and_then(|tool| match tool {
+                    toml::Value::Table(mut tool) => tool.remove("yars"),
+                    _ => None,
+                });
+            return match section {
+                Some(section) => section.try_into().map(Some).map_err(invalid),
+                None => Ok(None),
+            };
+        }
+
+        toml::from_str(&text).map(Some).map_err(invalid)
+    }
+
+    /// Overlay `self` on top of `parent`; fields set in `self` win.
+    pub fn merged_over(&self, parent: &Config) -> Config {
+        Config {
+            root: self.root || parent.root,
+            indent: self.indent.or(parent.indent),
+            line_ending: self.line_ending.or(parent.line_ending),
+            final_newline: self.final_newline.or(parent.final_newline),
+            encoding: self.encoding.or(parent.encoding),
+            document_markers: self.document_markers.or(parent.document_markers),
+            duplicate_keys: self.duplicate_keys.or(parent.duplicate_keys),
+        }
+    }
+
+    /// Resolve to concrete formatter options, filling unset fields with defaults.
+    fn format_options(&self) -> Result<FormatOptions, String> {
+        let defaults = FormatOptions::default();
+        let indent = self.indent.unwrap_or(defaults.indent);
+        if indent == 0 {
+            return Err("indent must be at least 1".to_owned());
+        }
+        Ok(FormatOptions {
+            indent,
+            line_ending: self.line_ending.unwrap_or(defaults.line_ending),
+            final_newline: self.final_newline.unwrap_or(defaults.final_newline),
+            encoding: self.encoding.or(defaults.encoding),
+            document_markers: self.document_markers.unwrap_or(defaults.document_markers),
+            duplicate_keys: self.duplicate_keys.unwrap_or(defaults.duplicate_keys),
+        })
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// The configuration that applies to one path, and where it came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    /// Files that contributed settings, nearest last.
+    pub sources: Vec<PathBuf>,
+}
+
+impl ResolvedConfig {
+    /// Concrete formatter options for this configuration.
+    pub fn format_options(&self) -> Result<FormatOptions, YamlFormatError> {
+        self.config.format_options().map_err(|message| {
+            let origin = self
+                .sources
+                .last()
+                .map_or_else(|| "<defaults>".to_owned(), |path| path.display().to_string());
+            YamlFormatError::InvalidConfig(origin, message)
+        })
+    }
+}
+
+/// Resolves configuration for many paths, caching per directory.
+#[derive(Debug, Default)]
+pub struct ConfigResolver {
+    explicit: Option<ResolvedConfig>,
+    cache: HashMap<PathBuf, ResolvedConfig>,
+    editorconfig: EditorConfigResolver,
+}
+
+impl ConfigResolver {
+    /// Resolver that discovers configuration files next to each path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolver that applies a single configuration file to every path,
+    /// bypassing discovery.
+    pub fn with_config_file(path: &Path) -> Result<Self, YamlFormatError> {
+        let config = Config::from_file(path)?.unwrap_or_default();
+        Ok(Self {
+            explicit: Some(ResolvedConfig {
+                config,
+                sources: vec![path.to_path_buf()],
+            }),
+            cache: HashMap::new(),
+            editorconfig: EditorConfigResolver::default(),
+        })
+    }
+
+    /// Effective configuration for the file (or directory) at `path`.
+    pub fn resolve(&mut self, path: &Path) -> Result<ResolvedConfig, YamlFormatError> {
+        let absolute = std::path::absolute(path).map_err(|err| {
+            YamlFormatError::ReadFailure(path.display().to_string(), err.to_string())
+        })?;
+        let yars = match &self.explicit {
+            Some(explicit) => explicit.clone(),
+            None => {
+                let dir = if absolute.is_dir() {
+                    absolute.as_path()
+                } else {
+                    absolute.parent().unwrap_or(absolute.as_path())
+                };
+                self.resolve_dir(dir)?
+            }
+        };
+
+        let editor = self.editorconfig.resolve(&absolute)?;
+        let mut sources = editor.sources;
+        sources.extend(yars.sources);
+        Ok(ResolvedConfig {
+            config: yars.config.merged_over(&editor.properties.to_config()),
+            sources,
+        })
+    }
+
+    fn resolve_dir(&mut self, dir: &Path) -> Result<ResolvedConfig, YamlFormatError> {
+        if let Some(cached) = self.cache.get(dir) {
+            return Ok(cached.clone());
+        }
+
+        let local = find_config_in(dir)?;
+        let resolved = match local {
+            Some((path, config)) if config.root => ResolvedConfig {
+                config,
+                sources: vec![path],
+            },
+            local => {
+                let mut resolved = match dir.parent() {
+                    Some(parent) => self.resolve_dir(parent)?,
+                    None => ResolvedConfig::default(),
+                };
+                if let Some((path, config)) = local {
+                    resolved.config = config.merged_over(&resolved.config);
+                    resolved.sources.push(path);
+                }
+                resolved
+            }
+        };
+
+        self.cache.insert(dir.to_path_buf(), resolved.clone());
+        Ok(resolved)
+    }
+}
+
+/// Locate the configuration declared directly in `dir`, preferring
+/// `.yars.toml` over `pyproject.toml`.
+fn find_config_in(dir: &Path) -> Result<Option<(PathBuf, Config)>, YamlFormatError> {
+    for name in [CONFIG_FILE_NAME, PYPROJECT_FILE_NAME] {
+        let candidate = dir.join(name);
+        if !candidate.is_file() {
+            continue;
+        }
+        if let Some(config) = Config::from_file(&candidate)? {
+            return Ok(Some((candidate, config)));
+        }
+    }
+    Ok(None)
+}
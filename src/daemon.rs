@@ -0,0 +1,279 @@
+//! Long-running formatting daemon on a Unix domain socket.
+//!
+//! Clients send newline-delimited JSON-RPC 2.0 requests and read one
+//! response line per request; a connection may carry any number of them.
+//!
+//! - `format` `{text, options?, path?}` returns `{formatted, changed}`
+//! - `check` `{text, options?, path?}` returns `{changed}`
+//! - `shutdown` stops the daemon once in-flight connections finish
+//!
+//! Without explicit `options`, settings are resolved for `path` exactly as
+//! the CLI resolves them, or the defaults when neither is given. Failures are
+//! JSON-RPC errors whose `data` is an [`ErrorRecord`].
+
+use crate::config::ConfigResolver;
+use crate::report::ErrorRecord;
+use crate::{format_yaml_string_with_options, ErrorCode, FormatOptions, YamlFormatError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Application error: the document could not be formatted.
+const FORMAT_FAILED: i64 = 1;
+
+/// Socket used when none is given: `$XDG_RUNTIME_DIR/yars-format.sock`,
+/// falling back to `yars-format-<uid>/daemon.sock` in the temporary
+/// directory. [`serve`] creates that directory readable only by its owner.
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("yars-format.sock"),
+        _ => fallback_dir().join("daemon.sock"),
+    }
+}
+
+fn fallback_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("yars-format-{}", current_uid()))
+}
+
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() }
+}
+
+/// Create `dir` with mode 0700, or check that an existing one is owned by
+/// this user and closed to everyone else.
+fn ensure_private_dir(dir: &Path) -> io::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => return Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+        Err(err) => return Err(err),
+    }
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory private to this user", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Listen on `socket` until a client sends `shutdown`. A stale socket file
+/// left by a daemon that died is replaced; a live one is an error.
+pub fn serve(socket: &Path) -> Result<(), YamlFormatError> {
+    let io_error = |err: io::Error| YamlFormatError::Daemon(format!("{}: {}", socket.display(), err));
+    if socket.parent() == Some(fallback_dir().as_path()) {
+        ensure_private_dir(&fallback_dir()).map_err(io_error)?;
+    }
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(YamlFormatError::Daemon(format!(
+                "{}: a daemon is already listening",
+                socket.display()
+            )));
+        }
+        fs::remove_file(socket).map_err(io_error)?;
+    }
+    let listener = UnixListener::bind(socket).map_err(io_error)?;
+
+    let stopping = Arc::new(AtomicBool::new(false));
+    let mut workers = Vec::new();
+    for stream in listener.incoming() {
+        if stopping.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let stopping = Arc::clone(&stopping);
+        let socket = socket.to_path_buf();
+        workers.push(thread::spawn(move || {
+            if handle_connection(stream) && !stopping.swap(true, Ordering::SeqCst) {
+                // Wake the accept loop so it notices the flag.
+                let _ = UnixStream::connect(&socket);
+            }
+        }));
+        workers.retain(|worker| !worker.is_finished());
+    }
+
+    for worker in workers {
+        let _ = worker.join();
+    }
+    let _ = fs::remove_file(socket);
+    Ok(())
+}
+
+/// Serve one connection; returns whether it asked the daemon to shut down.
+fn handle_connection(stream: UnixStream) -> bool {
+    let Ok(mut writer) = stream.try_clone() else {
+        return false;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return false;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, shutdown) = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => respond(&request),
+            Err(err) => (error_response(Value::Null, PARSE_ERROR, &err.to_string(), None), false),
+        };
+        if writeln!(writer, "{reply}").is_err() {
+            return false;
+        }
+        if shutdown {
+            return true;
+        }
+    }
+    false
+}
+
+#[derive(Deserialize)]
+struct FormatParams {
+    text: String,
+    #[serde(default)]
+    options: Option<FormatOptions>,
+    #[serde(default)]
+    path: Option<PathBuf>,
+}
+
+fn respond(request: &Value) -> (Value, bool) {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "shutdown" => return (json!({"jsonrpc": "2.0", "id": id, "result": null}), true),
+        "format" | "check" => match serde_json::from_value::<FormatParams>(params) {
+            Ok(params) => format(&params).map(|formatted| {
+                let changed = formatted != params.text;
+                match method {
+                    "format" => json!({"formatted": formatted, "changed": changed}),
+                    _ => json!({"changed": changed}),
+                }
+            }),
+            Err(err) => return (error_response(id, INVALID_PARAMS, &err.to_string(), None), false),
+        },
+        _ => {
+            let message = format!("unknown method {method:?}");
+            return (error_response(id, METHOD_NOT_FOUND, &message, None), false);
+        }
+    };
+
+    let reply = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(err) => {
+            let (line, column) = err.location().unzip();
+            let record = ErrorRecord {
+                kind: err.kind().to_owned(),
+                code: err.code(),
+                message: err.to_string(),
+                line,
+                column,
+                snippet: None,
+            };
+            let message = record.message.clone();
+            error_response(id, FORMAT_FAILED, &message, Some(record))
+        }
+    };
+    (reply, false)
+}
+
+fn format(params: &FormatParams) -> Result<String, YamlFormatError> {
+    let options = match (&params.options, &params.path) {
+        (Some(options), _) => options.clone(),
+        (None, Some(path)) => ConfigResolver::new().resolve(path)?.format_options()?,
+        (None, None) => FormatOptions::default(),
+    };
+    format_yaml_string_with_options(&params.text, &options)
+}
+
+fn error_response(id: Value, code: i64, message: &str, data: Option<ErrorRecord>) -> Value {
+    let mut error = json!({"code": code, "message": message});
+    if let Some(data) = data {
+        error["data"] = json!(data);
+    }
+    json!({"jsonrpc": "2.0", "id": id, "error": error})
+}
+
+/// Connection to a running daemon.
+pub struct Client {
+    writer: UnixStream,
+    reader: BufReader<UnixStream>,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connect to the daemon listening on `socket`. A socket owned by
+    /// another user is refused, since its daemon would see every document.
+    pub fn connect(socket: &Path) -> io::Result<Self> {
+        if fs::metadata(socket)?.uid() != current_uid() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is owned by another user", socket.display()),
+            ));
+        }
+        let writer = UnixStream::connect(socket)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self {
+            writer,
+            reader,
+            next_id: 0,
+        })
+    }
+
+    /// Format `text` with `options`. The outer error reports a broken
+    /// connection; the inner one a document the daemon could not format,
+    /// with the code and message the daemon gave.
+    pub fn format(
+        &mut self,
+        text: &str,
+        options: &FormatOptions,
+    ) -> io::Result<Result<String, YamlFormatError>> {
+        let reply = self.call("format", json!({"text": text, "options": options}))?;
+        Ok(reply
+            .map(|result| result["formatted"].as_str().unwrap_or(text).to_owned())
+            .map_err(|record| YamlFormatError::Remote(Box::new(record))))
+    }
+
+    /// Ask the daemon to stop.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        self.call("shutdown", Value::Null).map(|_| ())
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> io::Result<Result<Value, ErrorRecord>> {
+        self.next_id += 1;
+        let request = json!({"jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params});
+        writeln!(self.writer, "{request}")?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "daemon closed the connection"));
+        }
+        let mut reply: Value = serde_json::from_str(&line)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if let Some(error) = reply.get_mut("error") {
+            let record = serde_json::from_value(error["data"].take()).unwrap_or_else(|_| ErrorRecord {
+                kind: "daemon".to_owned(),
+                code: ErrorCode::Daemon,
+                message: error["message"].as_str().unwrap_or("daemon request failed").to_owned(),
+                line: None,
+                column: None,
+                snippet: None,
+            });
+            return Ok(Err(record));
+        }
+        Ok(Ok(reply["result"].take()))
+    }
+}
@@ -0,0 +1,165 @@
+//! Line diff between a document and its formatted form.
+//!
+//! One Myers diff over whole lines (terminators included) backs both the
+//! change statistics reported by the CLI and the [`TextEdit`]s handed to
+//! editors, so the two always agree on what changed.
+
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+use std::ops::Range as Span;
+
+/// Unit in which [`Position::column`] counts characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PositionEncoding {
+    /// Bytes of UTF-8.
+    Utf8,
+    /// UTF-16 code units, as the Language Server Protocol counts by default.
+    #[default]
+    Utf16,
+}
+
+/// Zero-based location in the original text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// Byte offset of this position in `text`, clamped to the end of its line
+    /// (or of the text).
+    pub fn offset(&self, text: &str, encoding: PositionEncoding) -> usize {
+        let Some(line) = text.split_inclusive('\n').nth(self.line) else {
+            return text.len();
+        };
+        let line_start = line.as_ptr() as usize - text.as_ptr() as usize;
+        let content = line.trim_end_matches(['\r', '\n']);
+        let mut units = 0;
+        for (idx, ch) in content.char_indices() {
+            if units >= self.column {
+                return line_start + idx;
+            }
+            units += match encoding {
+                PositionEncoding::Utf8 => ch.len_utf8(),
+                PositionEncoding::Utf16 => ch.len_utf16(),
+            };
+        }
+        line_start + content.len()
+    }
+}
+
+/// Half-open span of the original text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Replace `range` of the original text with `new_text`. Edits returned
+/// together never overlap and are ordered by position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+/// How much formatting changed a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChangeStats {
+    /// Lines touched: each hunk counts the larger of its removed and added lines.
+    pub lines_changed: usize,
+    /// 1-based line of the original where the first change starts, clamped
+    /// to the last line for changes appended at the end.
+    pub first_changed_line: Option<usize>,
+}
+
+/// Statistics for the changes turning `original` into `formatted`.
+pub fn change_stats(original: &str, formatted: &str) -> ChangeStats {
+    let diff = LineDiff::new(original, formatted);
+    ChangeStats {
+        lines_changed: diff
+            .hunks
+            .iter()
+            .map(|hunk| hunk.old.len().max(hunk.new.len()))
+            .sum(),
+        first_changed_line: diff
+            .hunks
+            .first()
+            .map(|hunk| (hunk.old.start + 1).min(diff.old.len().max(1))),
+    }
+}
+
+/// Minimal line-granular edits turning `original` into `formatted`, with
+/// columns counted in `encoding` units.
+pub fn text_edits(original: &str, formatted: &str, encoding: PositionEncoding) -> Vec<TextEdit> {
+    let diff = LineDiff::new(original, formatted);
+    diff.hunks
+        .iter()
+        .map(|hunk| TextEdit {
+            range: Range {
+                start: diff.line_start(hunk.old.start, encoding),
+                end: diff.line_start(hunk.old.end, encoding),
+            },
+            new_text: diff.new[hunk.new.clone()].concat(),
+        })
+        .collect()
+}
+
+struct Hunk {
+    old: Span<usize>,
+    new: Span<usize>,
+}
+
+struct LineDiff<'a> {
+    old: Vec<&'a str>,
+    new: Vec<&'a str>,
+    hunks: Vec<Hunk>,
+}
+
+impl<'a> LineDiff<'a> {
+    fn new(original: &'a str, formatted: &'a str) -> Self {
+        let old: Vec<&str> = original.split_inclusive('\n').collect();
+        let new: Vec<&str> = formatted.split_inclusive('\n').collect();
+
+        // Adjacent deletions and insertions form a single hunk.
+        let mut hunks: Vec<Hunk> = Vec::new();
+        for op in capture_diff_slices(Algorithm::Myers, &old, &new) {
+            if op.tag() == DiffTag::Equal {
+                continue;
+            }
+            let (old_span, new_span) = (op.old_range(), op.new_range());
+            match hunks.last_mut() {
+                Some(last) if last.old.end == old_span.start && last.new.end == new_span.start => {
+                    last.old.end = old_span.end;
+                    last.new.end = new_span.end;
+                }
+                _ => hunks.push(Hunk {
+                    old: old_span,
+                    new: new_span,
+                }),
+            }
+        }
+        Self { old, new, hunks }
+    }
+
+    /// Position of the start of original line `line`, or of the end of the
+    /// text when `line` is past the last line.
+    fn line_start(&self, line: usize, encoding: PositionEncoding) -> Position {
+        if line < self.old.len() {
+            return Position { line, column: 0 };
+        }
+        match self.old.last() {
+            Some(last) if !last.ends_with('\n') => Position {
+                line: self.old.len() - 1,
+                column: match encoding {
+                    PositionEncoding::Utf8 => last.len(),
+                    PositionEncoding::Utf16 => last.encode_utf16().count(),
+                },
+            },
+            _ => Position {
+                line: self.old.len(),
+                column: 0,
+            },
+        }
+    }
+}
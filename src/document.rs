@@ -0,0 +1,103 @@
+//! Directives and document markers around the formatted document.
+//!
+//! The parser consumes `%YAML`/`%TAG` directives and the `---` and `...`
+//! markers, so they are read back from the source text and written around
+//! the emitted document as [`DocumentMarkers`] asks.
+
+use crate::{tags, DocumentMarkers};
+use serde_yaml::Value;
+
+/// What surrounds a document: directive lines, a `---` start marker and a
+/// `...` end marker.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub directives: Vec<String>,
+    pub start: bool,
+    pub end: bool,
+}
+
+impl Frame {
+    /// The directives and markers present in `source`. Comments and blank
+    /// lines around them are skipped.
+    pub fn scan(source: &str) -> Frame {
+        let mut frame = Frame::default();
+        let mut lines = source
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for line in lines.by_ref() {
+            if line.starts_with('%') {
+                frame.directives.push(line.to_owned());
+            } else {
+                frame.start = is_marker(line, "---");
+                break;
+            }
+        }
+        frame.end = lines.next_back().is_some_and(|line| is_marker(line, "..."));
+        frame
+    }
+
+    /// The frame to write around `value` under `markers`. `%TAG` directives
+    /// whose handles `value`'s tags are spelled with are always kept.
+    /// Directives are only valid before an explicit start marker, so keeping
+    /// any implies one.
+    pub fn for_output(self, markers: DocumentMarkers, value: &Value) -> Frame {
+        match markers {
+            DocumentMarkers::Never => {
+                let directives: Vec<String> = self
+                    .directives
+                    .into_iter()
+                    .filter(|directive| {
+                        directive.starts_with("%TAG") && tags::uses_directive(value, directive)
+                    })
+                    .collect();
+                Frame {
+                    start: !directives.is_empty(),
+                    directives,
+                    end: false,
+                }
+            }
+            DocumentMarkers::Always => Frame {
+                start: true,
+                end: true,
+                ..self
+            },
+            DocumentMarkers::Preserve => Frame {
+                start: self.start || !self.directives.is_empty(),
+                ..self
+            },
+        }
+    }
+}
+
+/// Whether `line` (at column zero) is the document marker `marker`.
+fn is_marker(line: &str, marker: &str) -> bool {
+    line.strip_prefix(marker)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// 1-based line and column where the document's root node starts in
+/// `source`, past directives, comments and a `---` marker.
+pub(crate) fn content_start(source: &str) -> (usize, usize) {
+    let mut directives = true;
+    for (idx, line) in source.lines().enumerate() {
+        let mut text = line.trim_end();
+        let mut column = 1;
+        if directives && text.starts_with('%') {
+            continue;
+        }
+        if is_marker(text, "---") {
+            directives = false;
+            let rest = &text[3..];
+            column = 4 + rest.len() - rest.trim_start().len();
+            text = rest.trim_start();
+        }
+        let content = text.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        column += text[..text.len() - content.len()].chars().count();
+        return (idx + 1, column);
+    }
+    (1, 1)
+}
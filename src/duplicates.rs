@@ -0,0 +1,334 @@
+//! Mapping keys that occur more than once.
+//!
+//! The parser rejects a document with a repeated key without saying where
+//! the repetition is. When it does, the document is parsed again with a
+//! lenient reader that notes each repetition and applies the
+//! [`DuplicateKeys`] policy. The notes are then matched to the `key:` lines
+//! found by scanning the source's lines, to report both places the
+//! key appears. Keys in flow collections or spelled as quoted multi-line
+//! scalars are not found by the scan and are reported without a position.
+
+use crate::scan::{is_block_scalar_header, node_starts, push_key, Line, NodeKind};
+use crate::{DuplicateKeys, YamlFormatError};
+use serde::de::{self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+
+/// A key repeated within one mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// Path of the key from the document root, such as `jobs.build[0].name`.
+    pub key: String,
+    /// 1-based line and column of the key's first occurrence, when found.
+    pub first: Option<(usize, usize)>,
+    /// 1-based line and column of the repeated occurrence, when found.
+    pub duplicate: Option<(usize, usize)>,
+}
+
+impl fmt::Display for DuplicateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.key)?;
+        if let Some((line, column)) = self.duplicate {
+            write!(f, " at line {line}, column {column}")?;
+        }
+        if let Some((line, column)) = self.first {
+            write!(f, " (first defined at line {line}, column {column})")?;
+        }
+        Ok(())
+    }
+}
+
+/// `duplicates` as a sentence-ready list.
+pub(crate) fn describe(duplicates: &[DuplicateKey]) -> String {
+    duplicates
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Parse `text`, resolving repeated keys as `policy` says. `source` is the
+/// text as the user wrote it, whose lines `text` keeps.
+pub(crate) fn parse(text: &str, policy: DuplicateKeys, source: &str) -> Result<Value, YamlFormatError> {
+    let err = match serde_yaml::from_str::<Value>(text) {
+        Ok(value) => return Ok(value),
+        Err(err) if is_duplicate(&err) => err,
+        Err(err) => return Err(YamlFormatError::parse(err)),
+    };
+
+    let mut found = Vec::new();
+    let seed = Lenient {
+        policy,
+        path: String::new(),
+        found: &mut found,
+    };
+    let value = match seed.deserialize(serde_yaml::Deserializer::from_str(text)) {
+        Ok(value) => value,
+        // Anything else wrong with the document is reported as it was.
+        Err(_) => return Err(YamlFormatError::parse(err)),
+    };
+    match policy {
+        DuplicateKeys::Error => Err(YamlFormatError::DuplicateKeys {
+            path: None,
+            duplicates: locate(&found, source),
+        }),
+        DuplicateKeys::First | DuplicateKeys::Last => Ok(value),
+    }
+}
+
+/// Whether the parser failed on a repeated key. Errors below the root
+/// carry the path of their node as a `path: ` prefix.
+fn is_duplicate(err: &serde_yaml::Error) -> bool {
+    let message = err.to_string();
+    message.starts_with("duplicate entry") || message.contains(": duplicate entry")
+}
+
+/// A repeated key as the lenient reader saw it: the path of its mapping and
+/// the key itself.
+struct Found {
+    parent: String,
+    key: Value,
+}
+
+/// Reads any value like [`Value`]'s own `Deserialize`, except that a key
+/// seen earlier in the same mapping is noted in `found` instead of failing.
+struct Lenient<'a> {
+    policy: DuplicateKeys,
+    /// Path of the value being read, built like [`push_key`] builds it.
+    path: String,
+    found: &'a mut Vec<Found>,
+}
+
+impl Lenient<'_> {
+    fn child(&mut self, path: String) -> Lenient<'_> {
+        Lenient {
+            policy: self.policy,
+            path,
+            found: self.found,
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Lenient<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Lenient<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::new();
+        loop {
+            let path = format!("{}[{}]", self.path, items.len());
+            match seq.next_element_seed(self.child(path))? {
+                Some(item) => items.push(item),
+                None => return Ok(Value::Sequence(items)),
+            }
+        }
+    }
+
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<Value, A::Error> {
+        let mut mapping = Mapping::new();
+        while let Some(key) = map.next_key_seed(self.child(self.path.clone()))? {
+            let mut path = self.path.clone();
+            push_key(&mut path, &key);
+            let value = map.next_value_seed(self.child(path))?;
+            if mapping.contains_key(&key) {
+                self.found.push(Found {
+                    parent: self.path.clone(),
+                    key: key.clone(),
+                });
+                if self.policy != DuplicateKeys::Last {
+                    continue;
+                }
+            }
+            mapping.insert(key, value);
+        }
+        Ok(Value::Mapping(mapping))
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Value, A::Error> {
+        let (tag, contents) = data.variant::<String>()?;
+        if tag.is_empty() {
+            return Err(de::Error::custom("empty YAML tag is not allowed"));
+        }
+        let value = contents.newtype_variant_seed(self)?;
+        Ok(Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        })))
+    }
+}
+
+/// A `key:` line found by scanning the source.
+struct Entry {
+    parent: String,
+    key: Value,
+    line: usize,
+    column: usize,
+}
+
+/// Pair each repetition in `found` with the occurrences of its key in
+/// `source`: the first, and the one it repeats in order. Sorted by
+/// position, with those not found last.
+fn locate(found: &[Found], source: &str) -> Vec<DuplicateKey> {
+    let entries = scan(source);
+    let mut duplicates: Vec<DuplicateKey> = found
+        .iter()
+        .enumerate()
+        .map(|(idx, dup)| {
+            let same = |entry: &&Entry| entry.parent == dup.parent && entry.key == dup.key;
+            let earlier = found[..idx]
+                .iter()
+                .filter(|other| other.parent == dup.parent && other.key == dup.key)
+                .count();
+            let mut occurrences = entries.iter().filter(same).map(|entry| (entry.line, entry.column));
+            let first = occurrences.next();
+            let mut key = dup.parent.clone();
+            push_key(&mut key, &dup.key);
+            DuplicateKey {
+                key,
+                first,
+                duplicate: occurrences.nth(earlier),
+            }
+        })
+        .collect();
+    duplicates.sort_by_key(|duplicate| (duplicate.duplicate.is_none(), duplicate.duplicate));
+    duplicates
+}
+
+/// Enclosing node of a scanned line: a mapping entry with its key, or a
+/// sequence item with its index.
+enum Frame {
+    Entry { column: usize, key: Value },
+    Item { column: usize, index: usize },
+}
+
+impl Frame {
+    fn column(&self) -> usize {
+        match self {
+            Frame::Entry { column, .. } | Frame::Item { column, .. } => *column,
+        }
+    }
+}
+
+/// The block mapping entries of `source`, with the path of the mapping each
+/// belongs to.
+fn scan(source: &str) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut scalar_parent: Option<usize> = None;
+
+    for (idx, line) in Line::split(source).iter().enumerate() {
+        if let Some(parent) = scalar_parent {
+            if line.content.is_empty() || line.indent > parent {
+                continue;
+            }
+            scalar_parent = None;
+        }
+        if line.is_blank_or_comment() || line.content.starts_with('%') {
+            continue;
+        }
+
+        let starts = node_starts(line);
+        if let Some(&(column, _, rest)) = starts.last() {
+            if is_block_scalar_header(rest) {
+                scalar_parent = Some(column);
+            }
+        } else if is_block_scalar_header(line.content) {
+            scalar_parent = Some(line.indent);
+        }
+
+        for (column, kind, rest) in starts {
+            let frame = match kind {
+                NodeKind::Item => {
+                    // An item at a key's column is an indentless sequence
+                    // under that key.
+                    let mut index = 0;
+                    while let Some(top) = stack.last() {
+                        match top {
+                            Frame::Item { column: at, index: prev } if *at == column => index = prev + 1,
+                            top if top.column() > column => {}
+                            _ => break,
+                        }
+                        stack.pop();
+                    }
+                    Frame::Item { column, index }
+                }
+                NodeKind::Entry => {
+                    stack.retain(|frame| frame.column() < column);
+                    let text = &line.content[column - line.indent..];
+                    let key = text[..text.len() - rest.len()].trim_end().trim_end_matches(':');
+                    let Ok(key) = serde_yaml::from_str::<Value>(key) else {
+                        continue;
+                    };
+                    entries.push(Entry {
+                        parent: path_of(&stack),
+                        key: key.clone(),
+                        line: idx + 1,
+                        column: column + 1,
+                    });
+                    Frame::Entry { column, key }
+                }
+            };
+            stack.push(frame);
+        }
+    }
+    entries
+}
+
+fn path_of(stack: &[Frame]) -> String {
+    let mut path = String::new();
+    for frame in stack {
+        match frame {
+            Frame::Entry { key, .. } => push_key(&mut path, key),
+            Frame::Item { index, .. } => path.push_str(&format!("[{index}]")),
+        }
+    }
+    path
+}
@@ -0,0 +1,218 @@
+//! `.editorconfig` support.
+//!
+//! Only the properties that map onto [`FormatOptions`](crate::FormatOptions)
+//! are interpreted: `indent_size` (or `tab_width` when it is `tab`),
+//! `end_of_line`, `insert_final_newline` and the Unicode values of `charset`.
+//! YAML forbids tab indentation, so `indent_style` is ignored.
+
+use crate::config::Config;
+use crate::encoding::Encoding;
+use crate::{LineEnding, YamlFormatError};
+use globset::{GlobBuilder, GlobMatcher};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// File name searched for in every ancestor directory.
+pub const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+/// Properties that apply to a single file, with lower-cased names and values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties(HashMap<String, String>);
+
+impl Properties {
+    /// Raw value of a property, if set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Translate the recognised properties into yars settings.
+    pub fn to_config(&self) -> Config {
+        let indent = match self.get("indent_size") {
+            Some("tab") => self.get("tab_width"),
+            other => other,
+        }
+        .and_then(|size| size.parse::<usize>().ok())
+        .filter(|size| *size > 0);
+
+        let line_ending = match self.get("end_of_line") {
+            Some("lf") => Some(LineEnding::Lf),
+            Some("crlf") => Some(LineEnding::Crlf),
+            _ => None,
+        };
+
+        let final_newline = match self.get("insert_final_newline") {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        };
+
+        // YAML cannot be stored as latin1.
+        let encoding = match self.get("charset") {
+            Some("utf-8") => Some(Encoding::Utf8),
+            Some("utf-8-bom") => Some(Encoding::Utf8Bom),
+            Some("utf-16le") => Some(Encoding::Utf16Le),
+            Some("utf-16be") => Some(Encoding::Utf16Be),
+            _ => None,
+        };
+
+        Config {
+            indent,
+            line_ending,
+            final_newline,
+            encoding,
+            ..Config::default()
+        }
+    }
+
+    fn apply(&mut self, name: &str, value: &str) {
+        if value == "unset" {
+            self.0.remove(name);
+        } else {
+            self.0.insert(name.to_owned(), value.to_owned());
+        }
+    }
+}
+
+/// Properties for one path together with the files they came from.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedProperties {
+    pub properties: Properties,
+    /// `.editorconfig` files with at least one matching section, farthest first.
+    pub sources: Vec<PathBuf>,
+}
+
+/// Looks up `.editorconfig` properties, caching parsed files per directory.
+#[derive(Debug, Default)]
+pub struct EditorConfigResolver {
+    files: HashMap<PathBuf, Option<Rc<EditorConfigFile>>>,
+}
+
+impl EditorConfigResolver {
+    /// Properties applying to the absolute path `path`.
+    pub fn resolve(&mut self, path: &Path) -> Result<ResolvedProperties, YamlFormatError> {
+        let mut chain = Vec::new();
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if let Some(file) = self.load(current)? {
+                let root = file.root;
+                chain.push((current.to_path_buf(), file));
+                if root {
+                    break;
+                }
+            }
+            dir = current.parent();
+        }
+
+        let mut resolved = ResolvedProperties::default();
+        for (dir, file) in chain.into_iter().rev() {
+            let Ok(relative) = path.strip_prefix(&dir) else {
+                continue;
+            };
+            let mut matched = false;
+            for section in file.sections.iter().filter(|section| section.matcher.is_match(relative)) {
+                matched = true;
+                for (name, value) in &section.properties {
+                    resolved.properties.apply(name, value);
+                }
+            }
+            if matched {
+                resolved.sources.push(dir.join(EDITORCONFIG_FILE_NAME));
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn load(&mut self, dir: &Path) -> Result<Option<Rc<EditorConfigFile>>, YamlFormatError> {
+        if let Some(cached) = self.files.get(dir) {
+            return Ok(cached.clone());
+        }
+
+        let path = dir.join(EDITORCONFIG_FILE_NAME);
+        let file = if path.is_file() {
+            let text = fs::read_to_string(&path).map_err(|err| {
+                YamlFormatError::ReadFailure(path.display().to_string(), err.to_string())
+            })?;
+            Some(Rc::new(EditorConfigFile::parse(&text, &path)?))
+        } else {
+            None
+        };
+
+        self.files.insert(dir.to_path_buf(), file.clone());
+        Ok(file)
+    }
+}
+
+#[derive(Debug)]
+struct EditorConfigFile {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug)]
+struct Section {
+    matcher: GlobMatcher,
+    properties: Vec<(String, String)>,
+}
+
+impl EditorConfigFile {
+    fn parse(text: &str, path: &Path) -> Result<Self, YamlFormatError> {
+        let mut root = false;
+        let mut sections: Vec<Section> = Vec::new();
+
+        for (idx, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                let matcher = section_matcher(header).map_err(|message| {
+                    YamlFormatError::InvalidConfig(
+                        path.display().to_string(),
+                        format!("line {}: {}", idx + 1, message),
+                    )
+                })?;
+                sections.push(Section {
+                    matcher,
+                    properties: Vec::new(),
+                });
+                continue;
+            }
+
+            // Malformed lines are skipped, as editors do.
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let name = name.trim().to_ascii_lowercase();
+            let value = value.trim().to_ascii_lowercase();
+            match sections.last_mut() {
+                Some(section) => section.properties.push((name, value)),
+                None if name == "root" => root = value == "true",
+                None => {}
+            }
+        }
+
+        Ok(Self { root, sections })
+    }
+}
+
+/// Compile a section header into a matcher for paths relative to the
+/// directory of the `.editorconfig` file.
+fn section_matcher(header: &str) -> Result<GlobMatcher, String> {
+    let pattern = if let Some(anchored) = header.strip_prefix('/') {
+        anchored.to_owned()
+    } else if header.contains('/') {
+        header.to_owned()
+    } else {
+        format!("**/{header}")
+    };
+
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .backslash_escape(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,176 @@
+//! Character encodings of YAML files.
+//!
+//! YAML streams are UTF-8, UTF-16 or UTF-32. The encoding is given by a byte
+//! order mark or, without one, by the pattern of null bytes around the first
+//! character, which the specification requires to be ASCII (YAML 1.2,
+//! section 5.2). Files are decoded for formatting and encoded back the same
+//! way, with or without a byte order mark as before, unless a different
+//! encoding is configured. Files converted to UTF-16 or UTF-32 start with a
+//! byte order mark.
+
+use crate::YamlFormatError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// An encoding a YAML file can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Encoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    /// UTF-8 preceded by a byte order mark.
+    #[serde(rename = "utf-8-bom")]
+    Utf8Bom,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "utf-32le")]
+    Utf32Le,
+    #[serde(rename = "utf-32be")]
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Name used in configuration files and messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-bom",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Utf32Le => "utf-32le",
+            Encoding::Utf32Be => "utf-32be",
+        }
+    }
+
+    /// The encoding of `bytes`, with the length of its byte order mark.
+    pub fn detect(bytes: &[u8]) -> (Encoding, usize) {
+        match bytes {
+            [0, 0, 0xFE, 0xFF, ..] => (Encoding::Utf32Be, 4),
+            [0, 0, 0, _, ..] => (Encoding::Utf32Be, 0),
+            [0xFF, 0xFE, 0, 0, ..] => (Encoding::Utf32Le, 4),
+            [_, 0, 0, 0, ..] => (Encoding::Utf32Le, 0),
+            [0xFE, 0xFF, ..] => (Encoding::Utf16Be, 2),
+            [0, _, ..] => (Encoding::Utf16Be, 0),
+            [0xFF, 0xFE, ..] => (Encoding::Utf16Le, 2),
+            [_, 0, ..] => (Encoding::Utf16Le, 0),
+            [0xEF, 0xBB, 0xBF, ..] => (Encoding::Utf8Bom, 3),
+            _ => (Encoding::Utf8, 0),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Text read from a file, with how the file stored it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The text, without a byte order mark.
+    pub text: String,
+    pub encoding: Encoding,
+    /// Whether the bytes started with a byte order mark.
+    pub bom: bool,
+}
+
+impl Decoded {
+    /// Encode `text` the way this file was stored, or in `target` when that
+    /// is a different encoding.
+    pub fn encode(&self, text: &str, target: Option<Encoding>) -> Vec<u8> {
+        match target {
+            Some(target) if target != self.encoding => encode(text, target),
+            _ => encode_with(text, self.encoding, self.bom),
+        }
+    }
+}
+
+/// Decode the contents of `path`.
+pub fn decode(bytes: &[u8], path: &Path) -> Result<Decoded, YamlFormatError> {
+    let (encoding, bom) = Encoding::detect(bytes);
+    let invalid = |offset: usize| YamlFormatError::InvalidEncoding {
+        path: path.display().to_string(),
+        encoding,
+        offset: bom + offset,
+    };
+    let body = &bytes[bom..];
+    let text = match encoding {
+        Encoding::Utf8 | Encoding::Utf8Bom => match std::str::from_utf8(body) {
+            Ok(text) => text.to_owned(),
+            Err(err) => return Err(invalid(err.valid_up_to())),
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units = body.chunks(2).map(|unit| match (encoding, unit) {
+                (Encoding::Utf16Le, [lo, hi]) => Ok(u16::from_le_bytes([*lo, *hi])),
+                (_, [hi, lo]) => Ok(u16::from_be_bytes([*hi, *lo])),
+                _ => Err(()),
+            });
+            let mut text = String::with_capacity(body.len() / 2);
+            let mut offset = 0;
+            for ch in char::decode_utf16(units.map_while(Result::ok)) {
+                let ch = ch.map_err(|_| invalid(offset))?;
+                text.push(ch);
+                offset += ch.len_utf16() * 2;
+            }
+            if offset != body.len() {
+                return Err(invalid(offset));
+            }
+            text
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            let mut text = String::with_capacity(body.len() / 4);
+            for (idx, unit) in body.chunks(4).enumerate() {
+                let code = match (encoding, <[u8; 4]>::try_from(unit)) {
+                    (Encoding::Utf32Le, Ok(unit)) => u32::from_le_bytes(unit),
+                    (_, Ok(unit)) => u32::from_be_bytes(unit),
+                    (_, Err(_)) => return Err(invalid(idx * 4)),
+                };
+                text.push(char::from_u32(code).ok_or_else(|| invalid(idx * 4))?);
+            }
+            text
+        }
+    };
+    Ok(Decoded {
+        text,
+        encoding,
+        bom: bom > 0,
+    })
+}
+
+/// Encode `text` for storage, adding the byte order mark `encoding` calls
+/// for. UTF-16 and UTF-32 always get one.
+pub fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+    encode_with(text, encoding, true)
+}
+
+/// [`encode`], with a byte order mark before UTF-16 or UTF-32 only if `bom`.
+fn encode_with(text: &str, encoding: Encoding, bom: bool) -> Vec<u8> {
+    let bom = bom.then_some('\u{feff}');
+    match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+        Encoding::Utf16Le | Encoding::Utf16Be => bom
+            .into_iter()
+            .chain(text.chars())
+            .flat_map(|ch| {
+                let mut units = [0; 2];
+                ch.encode_utf16(&mut units).to_vec()
+            })
+            .flat_map(|unit| match encoding {
+                Encoding::Utf16Le => unit.to_le_bytes(),
+                _ => unit.to_be_bytes(),
+            })
+            .collect(),
+        Encoding::Utf32Le | Encoding::Utf32Be => bom
+            .into_iter()
+            .chain(text.chars())
+            .flat_map(|ch| match encoding {
+                Encoding::Utf32Le => u32::from(ch).to_le_bytes(),
+                _ => u32::from(ch).to_be_bytes(),
+            })
+            .collect(),
+    }
+}
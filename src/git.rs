@@ -0,0 +1,197 @@
+//! Git-aware file selection backed by the local `git` executable.
+//!
+//! Paths returned here are absolute (the repository root joined with the
+//! path git reports) and limited to YAML files that still exist: deletions
+//! are never selected.
+
+use crate::encoding::{self, Decoded};
+use crate::YamlFormatError;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Whether `path` has a `.yaml` or `.yml` extension.
+pub fn is_yaml_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+}
+
+/// A git working tree.
+#[derive(Debug, Clone)]
+pub struct Repository {
+    root: PathBuf,
+}
+
+impl Repository {
+    /// The repository containing `dir`.
+    pub fn discover(dir: &Path) -> Result<Self, YamlFormatError> {
+        let output = run_git(dir, &["rev-parse", "--show-toplevel"], None)?;
+        let root = String::from_utf8_lossy(&output).trim_end().to_owned();
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+
+    /// Top-level directory of the working tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// YAML files added, copied, modified or renamed in the index, optionally
+    /// restricted to `pathspecs`.
+    pub fn staged_files(&self, pathspecs: &[PathBuf]) -> Result<Vec<PathBuf>, YamlFormatError> {
+        self.diff_names(&["--cached"], pathspecs)
+    }
+
+    /// YAML files whose working-tree content differs from `rev`.
+    pub fn changed_since(
+        &self,
+        rev: &str,
+        pathspecs: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, YamlFormatError> {
+        self.diff_names(&[rev], pathspecs)
+    }
+
+    /// Files whose working-tree content differs from the index, i.e. files
+    /// that are only partially staged when they also appear in
+    /// [`staged_files`](Self::staged_files).
+    pub fn unstaged_files(&self) -> Result<HashSet<PathBuf>, YamlFormatError> {
+        Ok(self.diff_names(&[], &[])?.into_iter().collect())
+    }
+
+    /// Whether the working-tree content of `path` differs from the index,
+    /// as git compares them: after line-ending conversion and filters.
+    pub fn has_unstaged_changes(&self, path: &Path) -> Result<bool, YamlFormatError> {
+        Ok(!self.diff_names(&[], &[path.to_path_buf()])?.is_empty())
+    }
+
+    /// The staged content of `path`, decoded as [`encoding::decode`] decodes
+    /// files.
+    pub fn read_staged(&self, path: &Path) -> Result<Decoded, YamlFormatError> {
+        let spec = format!(":{}", self.relative(path)?);
+        let output = self.git(&["show", &spec], None)?;
+        encoding::decode(&output, Path::new(&format!("{} (staged)", path.display())))
+    }
+
+    /// Replace the staged content of `path` without touching the working tree.
+    pub fn stage_contents(&self, path: &Path, contents: &[u8]) -> Result<(), YamlFormatError> {
+        let relative = self.relative(path)?;
+        let entry = self.git(&["ls-files", "--stage", "--", &relative], None)?;
+        let entry = String::from_utf8_lossy(&entry);
+        let mode = entry
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| YamlFormatError::Git(format!("{relative} is not in the index")))?
+            .to_owned();
+
+        let blob = self.git(&["hash-object", "-w", "--stdin"], Some(contents))?;
+        let blob = String::from_utf8_lossy(&blob).trim_end().to_owned();
+        let cacheinfo = format!("{mode},{blob},{relative}");
+        self.git(&["update-index", "--cacheinfo", &cacheinfo], None)?;
+        Ok(())
+    }
+
+    fn diff_names(
+        &self,
+        selector: &[&str],
+        pathspecs: &[PathBuf],
+    ) -> Result<Vec<PathBuf>, YamlFormatError> {
+        let mut args = vec!["diff", "--name-only", "--diff-filter=ACMR", "-z"];
+        args.extend_from_slice(selector);
+        args.push("--");
+        let pathspecs: Vec<String> = pathspecs
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        args.extend(pathspecs.iter().map(String::as_str));
+
+        // Pathspecs are interpreted relative to the current directory.
+        let cwd = std::env::current_dir().map_err(|err| YamlFormatError::Git(err.to_string()))?;
+        let output = run_git(&cwd, &args, None)?;
+        Ok(output
+            .split(|byte| *byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| self.root.join(String::from_utf8_lossy(name).as_ref()))
+            .filter(|path| is_yaml_path(path))
+            .collect())
+    }
+
+    fn relative(&self, path: &Path) -> Result<String, YamlFormatError> {
+        let relative = path.strip_prefix(&self.root).map_err(|_| {
+            YamlFormatError::Git(format!(
+                "{} is outside the repository at {}",
+                path.display(),
+                self.root.display()
+            ))
+        })?;
+        // Git expects forward slashes regardless of platform.
+        Ok(relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"))
+    }
+
+    fn git(&self, args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>, YamlFormatError> {
+        run_git(&self.root, args, stdin)
+    }
+}
+
+fn run_git(dir: &Path, args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>, YamlFormatError> {
+    let describe = || format!("git {}", args.join(" "));
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| YamlFormatError::Git(format!("{}: {}", describe(), err)))?;
+
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input)
+            .map_err(|err| YamlFormatError::Git(format!("{}: {}", describe(), err)))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|err| YamlFormatError::Git(format!("{}: {}", describe(), err)))?;
+    if !output.status.success() {
+        return Err(YamlFormatError::Git(format!(
+            "{}: {}",
+            describe(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Textual three-way merge with `git merge-file`: the result, including any
+/// conflict markers, replaces `ours`. Returns the number of conflicts.
+pub fn merge_file(ours: &Path, base: &Path, theirs: &Path) -> Result<usize, YamlFormatError> {
+    let describe = || format!("git merge-file {}", ours.display());
+    let output = Command::new("git")
+        .args(["merge-file", "-L", "ours", "-L", "base", "-L", "theirs"])
+        .args([ours, base, theirs])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| YamlFormatError::Git(format!("{}: {}", describe(), err)))?;
+
+    // The exit status is the conflict count; failures are negative (255 when truncated).
+    match output.status.code() {
+        Some(count @ 0..=127) => Ok(count as usize),
+        _ => Err(YamlFormatError::Git(format!(
+            "{}: {}",
+            describe(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
@@ -5,13 +5,46 @@
 //! - `format_yaml_dict`
 //! - `format_yaml_file`
 //! - `format_yaml_files`
+//!
+//! [`format_yaml_text_edits`] returns the same result as minimal
+//! [`diff::TextEdit`]s against the input, for editors, and
+//! [`format_yaml_range`] reformats just the node enclosing a selection.
+//!
+//! `format_yaml_files` returns a [`BatchReport`] with a [`FileResult`] per
+//! file; [`Batch`] runs the same loop with a cache or a custom formatter.
+//!
+//! Each entry point has a `_with_options` variant that takes [`FormatOptions`];
+//! project settings are discovered through the [`config`] module, with
+//! `.editorconfig` defaults layered underneath by [`editorconfig`].
+
+pub mod atomic;
+pub mod batch;
+pub mod cache;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod diff;
+mod document;
+pub mod duplicates;
+pub mod editorconfig;
+pub mod encoding;
+pub mod git;
+pub mod lsp;
+pub mod merge;
+mod range;
+pub mod report;
+mod scan;
+mod tags;
+pub mod watch;
 
-use serde::Serialize;
+pub use batch::{Batch, BatchReport, FileResult};
+pub use range::{format_yaml_range, format_yaml_range_with_options};
+
+use serde::{Deserialize, Serialize};
 use serde_yaml::value::{Mapping, TaggedValue};
 use serde_yaml::Value;
 use std::borrow::Cow;
 use std::fmt::Write as _;
-use std::fs;
 use std::path::Path;
 use thiserror::Error;
 
@@ -20,39 +53,391 @@ use thiserror::Error;
 pub enum YamlFormatError {
     #[error("Error formatting YAML: {0}")]
     Format(String),
+    /// The input is not valid YAML. `line` and `column` are 1-based.
+    #[error("Error formatting YAML: {message}")]
+    Parse {
+        path: Option<String>,
+        line: usize,
+        column: usize,
+        message: String,
+        #[source]
+        source: serde_yaml::Error,
+    },
+    /// The document's root is a sequence. `line` and `column` (1-based) are
+    /// where it starts.
     #[error(
-        "Top-level lists are not supported by the YAML formatter. \
-UMF files should always have a dictionary at the root level with \
-'column:' and 'validations:' keys. If you're seeing this error, your YAML \
-file may be structured incorrectly."
+        "Top-level lists are not supported by the YAML formatter: the document \
+starting at line {line} is a list. UMF files should have a mapping with \
+'column:' and 'validations:' keys at the root."
     )]
-    TopLevelList,
+    TopLevelList {
+        path: Option<String>,
+        line: usize,
+        column: usize,
+    },
     #[error("File not found: {0}")]
     MissingFile(String),
     #[error("Failed to read {0}: {1}")]
     ReadFailure(String, String),
     #[error("Failed to write {0}: {1}")]
     WriteFailure(String, String),
+    #[error("Invalid configuration in {0}: {1}")]
+    InvalidConfig(String, String),
+    #[error("Git command failed: {0}")]
+    Git(String),
+    #[error("Failed to watch files: {0}")]
+    Watch(String),
+    #[error("Formatting daemon failed: {0}")]
+    Daemon(String),
+    #[error("{0} was modified by another process while it was being formatted; left unchanged")]
+    ConcurrentModification(String),
+    #[error("Invalid {encoding} in {path} at byte {offset}")]
+    InvalidEncoding {
+        path: String,
+        encoding: encoding::Encoding,
+        offset: usize,
+    },
+    /// Mappings repeat keys and [`DuplicateKeys::Error`] is in effect.
+    #[error("Duplicate keys: {}", duplicates::describe(.duplicates))]
+    DuplicateKeys {
+        path: Option<String>,
+        duplicates: Vec<duplicates::DuplicateKey>,
+    },
+    /// A document the formatting daemon could not format, as the daemon
+    /// reported it.
+    #[error("{}", .0.message)]
+    Remote(Box<report::ErrorRecord>),
+}
+
+impl YamlFormatError {
+    /// Stable snake_case name of the variant, for machine-readable reports.
+    pub fn kind(&self) -> &str {
+        match self {
+            YamlFormatError::Format(_) => "format",
+            YamlFormatError::Parse { .. } => "parse",
+            YamlFormatError::TopLevelList { .. } => "top_level_list",
+            YamlFormatError::MissingFile(_) => "missing_file",
+            YamlFormatError::ReadFailure(_, _) => "read_failure",
+            YamlFormatError::WriteFailure(_, _) => "write_failure",
+            YamlFormatError::InvalidConfig(_, _) => "invalid_config",
+            YamlFormatError::Git(_) => "git",
+            YamlFormatError::Watch(_) => "watch",
+            YamlFormatError::Daemon(_) => "daemon",
+            YamlFormatError::ConcurrentModification(_) => "concurrent_modification",
+            YamlFormatError::InvalidEncoding { .. } => "invalid_encoding",
+            YamlFormatError::DuplicateKeys { .. } => "duplicate_keys",
+            YamlFormatError::Remote(record) => &record.kind,
+        }
+    }
+
+    /// Stable code of the error, see [`ErrorCode`].
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            YamlFormatError::Parse { .. } => ErrorCode::Parse,
+            YamlFormatError::DuplicateKeys { .. } => ErrorCode::DuplicateKey,
+            YamlFormatError::TopLevelList { .. } => ErrorCode::UnsupportedRoot,
+            YamlFormatError::InvalidEncoding { .. } => ErrorCode::InvalidEncoding,
+            YamlFormatError::Format(_) => ErrorCode::Format,
+            YamlFormatError::MissingFile(_)
+            | YamlFormatError::ReadFailure(_, _)
+            | YamlFormatError::WriteFailure(_, _) => ErrorCode::Io,
+            YamlFormatError::ConcurrentModification(_) => ErrorCode::ConcurrentModification,
+            YamlFormatError::InvalidConfig(_, _) => ErrorCode::InvalidConfig,
+            YamlFormatError::Git(_) => ErrorCode::Git,
+            YamlFormatError::Watch(_) => ErrorCode::Watch,
+            YamlFormatError::Daemon(_) => ErrorCode::Daemon,
+            YamlFormatError::Remote(record) => record.code,
+        }
+    }
+
+    /// Wrap a parser error, keeping its position when it reports one.
+    fn parse(source: serde_yaml::Error) -> YamlFormatError {
+        match source.location() {
+            Some(location) => YamlFormatError::Parse {
+                path: None,
+                line: location.line(),
+                column: location.column(),
+                message: source.to_string(),
+                source,
+            },
+            None => YamlFormatError::Format(source.to_string()),
+        }
+    }
+
+    /// Reject the list at the root of `source`.
+    pub(crate) fn top_level_list(source: &str) -> YamlFormatError {
+        let (line, column) = document::content_start(source);
+        YamlFormatError::TopLevelList {
+            path: None,
+            line,
+            column,
+        }
+    }
+
+    /// 1-based line and column of the offending input, when known.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            YamlFormatError::Parse { line, column, .. }
+            | YamlFormatError::TopLevelList { line, column, .. } => Some((*line, *column)),
+            YamlFormatError::DuplicateKeys { duplicates, .. } => {
+                duplicates.iter().find_map(|duplicate| duplicate.duplicate)
+            }
+            YamlFormatError::Remote(record) => record.line.zip(record.column),
+            _ => None,
+        }
+    }
+
+    /// Record the file a parse, top-level list or duplicate key error
+    /// occurred in.
+    pub fn with_path(mut self, file: &Path) -> YamlFormatError {
+        if let YamlFormatError::Parse { path, .. }
+        | YamlFormatError::TopLevelList { path, .. }
+        | YamlFormatError::DuplicateKeys { path, .. } = &mut self
+        {
+            *path = Some(file.display().to_string());
+        }
+        self
+    }
 }
 
 /// Type alias with the Python-style name.
 pub type YAMLFormatError = YamlFormatError;
 
+/// Stable identifier of a class of error, written `Y001` and so on. Codes
+/// are never renumbered or reused, so tools can match on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// The input is not valid YAML.
+    #[serde(rename = "Y001")]
+    Parse,
+    /// The document's root is not a mapping.
+    #[serde(rename = "Y002")]
+    UnsupportedRoot,
+    /// A mapping has the same key more than once.
+    #[serde(rename = "Y003")]
+    DuplicateKey,
+    /// The bytes are not valid in the file's encoding.
+    #[serde(rename = "Y004")]
+    InvalidEncoding,
+    /// The input could not be formatted for another reason.
+    #[serde(rename = "Y009")]
+    Format,
+    /// A file could not be found, read or written.
+    #[serde(rename = "Y010")]
+    Io,
+    /// A file changed while it was being formatted.
+    #[serde(rename = "Y011")]
+    ConcurrentModification,
+    /// A configuration file could not be read or is invalid.
+    #[serde(rename = "Y020")]
+    InvalidConfig,
+    /// A git command failed or the directory is not a repository.
+    #[serde(rename = "Y030")]
+    Git,
+    /// Files could not be watched for changes.
+    #[serde(rename = "Y031")]
+    Watch,
+    /// The formatting daemon could not listen on its socket.
+    #[serde(rename = "Y032")]
+    Daemon,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Parse => "Y001",
+            ErrorCode::UnsupportedRoot => "Y002",
+            ErrorCode::DuplicateKey => "Y003",
+            ErrorCode::InvalidEncoding => "Y004",
+            ErrorCode::Format => "Y009",
+            ErrorCode::Io => "Y010",
+            ErrorCode::ConcurrentModification => "Y011",
+            ErrorCode::InvalidConfig => "Y020",
+            ErrorCode::Git => "Y030",
+            ErrorCode::Watch => "Y031",
+            ErrorCode::Daemon => "Y032",
+        }
+    }
+
+    pub fn category(self) -> ErrorCategory {
+        match self {
+            ErrorCode::Parse | ErrorCode::InvalidEncoding | ErrorCode::Format => ErrorCategory::Syntax,
+            ErrorCode::UnsupportedRoot | ErrorCode::DuplicateKey => ErrorCategory::Structure,
+            ErrorCode::Io | ErrorCode::ConcurrentModification => ErrorCategory::Io,
+            ErrorCode::InvalidConfig | ErrorCode::Git | ErrorCode::Watch | ErrorCode::Daemon => {
+                ErrorCategory::Environment
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Broad grouping of [`ErrorCode`]s, for deciding which failures are fatal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// The file's text could not be read as YAML.
+    Syntax,
+    /// The YAML is valid but has a shape the formatter does not accept.
+    Structure,
+    /// The file system got in the way.
+    Io,
+    /// Configuration, git, file watching or the daemon failed.
+    Environment,
+}
+
+/// Options controlling emission. The default reproduces the reference formatter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatOptions {
+    /// Spaces added per nesting level of mappings and sequences.
+    pub indent: usize,
+    /// Line terminator written between lines.
+    pub line_ending: LineEnding,
+    /// Whether the output ends with a line terminator.
+    pub final_newline: bool,
+    /// Encoding formatted files are written in; `None` keeps each file's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<encoding::Encoding>,
+    /// Whether `---`/`...` markers and directives are written.
+    #[serde(default, skip_serializing_if = "DocumentMarkers::is_default")]
+    pub document_markers: DocumentMarkers,
+    /// What to do with a key repeated within a mapping.
+    #[serde(default, skip_serializing_if = "DuplicateKeys::is_default")]
+    pub duplicate_keys: DuplicateKeys,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+            encoding: None,
+            document_markers: DocumentMarkers::Never,
+            duplicate_keys: DuplicateKeys::Error,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// These options with [`LineEnding::Auto`] resolved against `input`.
+    fn for_input(&self, input: &str) -> FormatOptions {
+        FormatOptions {
+            line_ending: self.line_ending.resolve(input),
+            ..self.clone()
+        }
+    }
+}
+
+/// Line terminator used in formatted output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Keep whichever terminator dominates the input (LF when it has none).
+    Auto,
+}
+
+impl LineEnding {
+    /// The terminator used by most line breaks in `text`; LF on a tie.
+    pub fn detect(text: &str) -> LineEnding {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// The concrete terminator to use when reformatting `input`.
+    pub fn resolve(self, input: &str) -> LineEnding {
+        match self {
+            LineEnding::Auto => LineEnding::detect(input),
+            concrete => concrete,
+        }
+    }
+}
+
+/// Handling of document start (`---`) and end (`...`) markers and of
+/// `%YAML`/`%TAG` directives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentMarkers {
+    /// Drop markers and directives, except the `%TAG` directives (and so
+    /// the `---`) that the emitted tags need.
+    #[default]
+    Never,
+    /// Always start the document with `---` and end it with `...`, keeping
+    /// any directives from the source.
+    Always,
+    /// Keep the markers and directives the source has.
+    Preserve,
+}
+
+impl DocumentMarkers {
+    fn is_default(&self) -> bool {
+        *self == DocumentMarkers::default()
+    }
+}
+
+/// Handling of keys repeated within a mapping, see [`duplicates`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateKeys {
+    /// Fail with [`YamlFormatError::DuplicateKeys`].
+    #[default]
+    Error,
+    /// Keep the value of the first occurrence.
+    First,
+    /// Keep the value of the last occurrence.
+    Last,
+}
+
+impl DuplicateKeys {
+    fn is_default(&self) -> bool {
+        *self == DuplicateKeys::default()
+    }
+}
+
 /// Format YAML text. Returns the original string when the parsed document is `null`.
 pub fn format_yaml_string(input: &str) -> Result<String, YamlFormatError> {
-    let to_parse = strip_leading_marker(input);
-    match serde_yaml::from_str::<Value>(to_parse) {
-        Ok(Value::Null) => Ok(input.to_owned()),
-        Ok(Value::Sequence(_)) => Err(YamlFormatError::TopLevelList),
-        Ok(Value::Mapping(map)) => {
+    format_yaml_string_with_options(input, &FormatOptions::default())
+}
+
+/// Format YAML text using explicit [`FormatOptions`].
+pub fn format_yaml_string_with_options(
+    input: &str,
+    options: &FormatOptions,
+) -> Result<String, YamlFormatError> {
+    let prepared = tags::prepare(input);
+    match duplicates::parse(&strip_leading_marker(&prepared), options.duplicate_keys, input)? {
+        Value::Null => Ok(input.to_owned()),
+        Value::Sequence(_) => Err(YamlFormatError::top_level_list(input)),
+        Value::Mapping(map) => {
             let sorted = sort_value(Value::Mapping(map));
-            emit_yaml(&sorted)
+            emit_yaml(&sorted, &options.for_input(input), input)
         }
-        Ok(other) => emit_yaml(&sort_value(other)),
-        Err(err) => Err(YamlFormatError::Format(err.to_string())),
+        other => emit_yaml(&sort_value(other), &options.for_input(input), input),
     }
 }
 
+/// Format YAML text, returning the edits that turn `input` into the formatted
+/// output rather than the output itself. Empty when already formatted.
+pub fn format_yaml_text_edits(
+    input: &str,
+    options: &FormatOptions,
+    encoding: diff::PositionEncoding,
+) -> Result<Vec<diff::TextEdit>, YamlFormatError> {
+    let formatted = format_yaml_string_with_options(input, options)?;
+    Ok(diff::text_edits(input, &formatted, encoding))
+}
+
 /// Format a serializable structure whose root must be a mapping.
 pub fn format_yaml_dict<T>(data: &T) -> Result<String, YamlFormatError>
 where
@@ -61,10 +446,10 @@ where
     match serde_yaml::to_value(data) {
         Ok(Value::Mapping(map)) => {
             let sorted = sort_value(Value::Mapping(map));
-            emit_yaml(&sorted)
+            emit_yaml(&sorted, &FormatOptions::default(), "")
         }
         Ok(Value::Null) => Ok(String::new()),
-        Ok(Value::Sequence(_)) => Err(YamlFormatError::TopLevelList),
+        Ok(Value::Sequence(_)) => Err(YamlFormatError::top_level_list("")),
         Ok(other) => Err(YamlFormatError::Format(format!(
             "Expected dict, got {}",
             describe_value(&other)
@@ -75,57 +460,48 @@ where
 
 /// Format a file in-place. Returns whether a change was (or would be) made.
 pub fn format_yaml_file(path: &Path, check_only: bool) -> Result<bool, YamlFormatError> {
-    if !path.exists() {
-        return Err(YamlFormatError::MissingFile(path.display().to_string()));
-    }
-
-    let original = fs::read_to_string(path)
-        .map_err(|err| YamlFormatError::ReadFailure(path.display().to_string(), err.to_string()))?;
-    let formatted = format_yaml_string(&original)?;
+    format_yaml_file_with_options(path, check_only, &FormatOptions::default())
+}
 
-    let changed = original != formatted;
-    if changed && !check_only {
-        fs::write(path, formatted)
-            .map_err(|err| YamlFormatError::WriteFailure(path.display().to_string(), err.to_string()))?;
+/// Format a file in-place using explicit [`FormatOptions`].
+pub fn format_yaml_file_with_options(
+    path: &Path,
+    check_only: bool,
+    options: &FormatOptions,
+) -> Result<bool, YamlFormatError> {
+    let result = Batch::new(check_only).format_file(path, options);
+    match result.error {
+        Some(err) => Err(err),
+        None => Ok(result.status == report::FileStatus::Changed),
     }
-
-    Ok(changed)
 }
 
-/// Format multiple files, aggregating errors.
-pub fn format_yaml_files<P>(paths: P, check_only: bool) -> (usize, usize, Vec<String>)
+/// Format multiple files, collecting a [`FileResult`] for each without
+/// stopping at failures.
+pub fn format_yaml_files<P>(paths: P, check_only: bool) -> BatchReport
 where
     P: IntoIterator,
     P::Item: AsRef<Path>,
 {
-    let mut changed = 0usize;
-    let mut errors = 0usize;
-    let mut messages = Vec::new();
-
-    for path in paths {
-        match format_yaml_file(path.as_ref(), check_only) {
-            Ok(true) => changed += 1,
-            Ok(false) => {}
-            Err(err) => {
-                errors += 1;
-                messages.push(err.to_string());
-            }
-        }
-    }
-
-    (changed, errors, messages)
+    Batch::new(check_only).run(paths, &FormatOptions::default())
 }
 
 // --- Normalisation helpers -------------------------------------------------
 
-fn strip_leading_marker(input: &str) -> &str {
+/// `input` without leading whitespace and a `---` marker. Removed line
+/// breaks are put back so parser positions still match the input's lines.
+fn strip_leading_marker(input: &str) -> Cow<'_, str> {
     let trimmed = input.trim_start();
-    if let Some(rest) = trimmed.strip_prefix("---\n") {
+    let rest = if let Some(rest) = trimmed.strip_prefix("---\n") {
         rest
     } else if let Some(rest) = trimmed.strip_prefix("---") {
         rest
     } else {
         trimmed
+    };
+    match input[..input.len() - rest.len()].matches('\n').count() {
+        0 => Cow::Borrowed(rest),
+        lines => Cow::Owned("\n".repeat(lines) + rest),
     }
 }
 
@@ -134,7 +510,7 @@ fn sort_value(value: Value) -> Value {
         Value::Mapping(map) => {
             let mut entries = Vec::with_capacity(map.len());
             for (key, val) in map {
-                entries.push((key_sort_key(&key), key, sort_value(val)));
+                entries.push(((key_sort_key(&key), key_rank(&key)), key, sort_value(val)));
             }
             entries.sort_by(|a, b| a.0.cmp(&b.0));
             let mut sorted = Mapping::with_capacity(entries.len());
@@ -166,28 +542,77 @@ fn key_sort_key(value: &Value) -> String {
     }
 }
 
+/// Order of keys whose [`key_sort_key`]s are equal, such as `1` and `"1"`.
+fn key_rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Sequence(_) => 4,
+        Value::Mapping(_) => 5,
+        Value::Tagged(_) => 6,
+    }
+}
+
 // --- Emission --------------------------------------------------------------
 
-fn emit_yaml(value: &Value) -> Result<String, YamlFormatError> {
-    let mut formatter = Formatter::new();
+/// Emit `value` as a document, framed by the markers and directives of the
+/// `source` it was parsed from as far as the options keep them.
+fn emit_yaml(value: &Value, options: &FormatOptions, source: &str) -> Result<String, YamlFormatError> {
+    let frame = document::Frame::scan(source).for_output(options.document_markers, value);
+    let mut formatter = Formatter::new(options);
+    for directive in &frame.directives {
+        formatter.buf.push_str(directive);
+        formatter.buf.push('\n');
+    }
+    if frame.start {
+        formatter.buf.push_str("---\n");
+    }
     formatter.write_root(value)?;
+    if frame.end {
+        if !formatter.buf.ends_with('\n') {
+            formatter.buf.push('\n');
+        }
+        formatter.buf.push_str("...");
+    }
     Ok(formatter.finish())
 }
 
 struct Formatter {
     buf: String,
+    indent: usize,
+    line_ending: LineEnding,
+    final_newline: bool,
 }
 
 impl Formatter {
-    fn new() -> Self {
-        Self { buf: String::new() }
+    fn new(options: &FormatOptions) -> Self {
+        Self {
+            buf: String::new(),
+            indent: options.indent,
+            line_ending: options.line_ending,
+            final_newline: options.final_newline,
+        }
     }
 
     fn finish(mut self) -> String {
-        if !self.buf.ends_with('\n') && !self.buf.is_empty() {
-            self.buf.push('\n');
+        if self.final_newline {
+            if !self.buf.ends_with('\n') && !self.buf.is_empty() {
+                self.buf.push('\n');
+            }
+        } else {
+            while self.buf.ends_with('\n') {
+                self.buf.pop();
+            }
+        }
+        // Quoted scalars escape their newlines, so every raw `\n` here is a
+        // line break (YAML folds CRLF back to LF inside literal blocks).
+        // `Auto` only reaches this point without input to take it from.
+        match self.line_ending {
+            LineEnding::Lf | LineEnding::Auto => self.buf,
+            LineEnding::Crlf => self.buf.replace('\n', "\r\n"),
         }
-        self.buf
     }
 
     fn write_root(&mut self, value: &Value) -> Result<(), YamlFormatError> {
@@ -248,6 +673,7 @@ impl Formatter {
                     self.buf.push_str(" {}");
                 } else {
                     self.buf.push(' ');
+                    // Keys stay aligned with the first key after "- ".
                     self.write_inline_mapping(map, indent + 2)?;
                 }
             }
@@ -256,13 +682,13 @@ impl Formatter {
                     self.buf.push_str(" []");
                 } else {
                     self.buf.push('\n');
-                    self.write_sequence(seq, indent + 2)?;
+                    self.write_sequence(seq, indent + self.indent)?;
                 }
             }
             Value::String(text) => {
                 if should_use_literal_block(text) {
                     self.buf.push_str(" |-\n");
-                    self.write_literal_block(text, indent + 2);
+                    self.write_literal_block(text, indent + self.indent);
                 } else {
                     self.buf.push(' ');
                     self.write_inline_string(text)?;
@@ -270,7 +696,7 @@ impl Formatter {
             }
             Value::Tagged(tagged) => {
                 self.buf.push(' ');
-                self.write_tagged(tagged, indent + 2)?;
+                self.write_tagged(tagged, indent + self.indent)?;
             }
             scalar => {
                 self.buf.push(' ');
@@ -317,7 +743,7 @@ impl Formatter {
                     self.buf.push_str(" {}");
                 } else {
                     self.buf.push('\n');
-                    self.write_mapping(map, indent + 2)?;
+                    self.write_mapping(map, indent + self.indent)?;
                 }
             }
             Value::Sequence(seq) => {
@@ -325,13 +751,13 @@ impl Formatter {
                     self.buf.push_str(" []");
                 } else {
                     self.buf.push('\n');
-                    self.write_sequence(seq, indent + 2)?;
+                    self.write_sequence(seq, indent + self.indent)?;
                 }
             }
             Value::String(text) => {
                 if should_use_literal_block(text) {
                     self.buf.push_str(" |-\n");
-                    self.write_literal_block(text, indent + 2);
+                    self.write_literal_block(text, indent + self.indent);
                 } else {
                     self.buf.push(' ');
                     self.write_inline_string(text)?;
@@ -339,7 +765,7 @@ impl Formatter {
             }
             Value::Tagged(tagged) => {
                 self.buf.push(' ');
-                self.write_tagged(tagged, indent + 2)?;
+                self.write_tagged(tagged, indent + self.indent)?;
             }
             scalar => {
                 self.buf.push(' ');
@@ -360,19 +786,19 @@ impl Formatter {
                 if matches!(position, Position::Root) {
                     self.write_mapping(map, indent)
                 } else {
-                    self.write_mapping(map, indent + 2)
+                    self.write_mapping(map, indent + self.indent)
                 }
             }
-            Value::Sequence(seq) => self.write_sequence(seq, indent + 2),
+            Value::Sequence(seq) => self.write_sequence(seq, indent + self.indent),
             Value::String(text) => {
                 if should_use_literal_block(text) {
-                    self.write_literal_block(text, indent + 2);
+                    self.write_literal_block(text, indent + self.indent);
                     Ok(())
                 } else {
                     self.write_inline_string(text)
                 }
             }
-            Value::Tagged(tagged) => self.write_tagged(tagged, indent + 2),
+            Value::Tagged(tagged) => self.write_tagged(tagged, indent + self.indent),
             scalar => self.write_scalar(scalar),
         }
     }
@@ -429,11 +855,29 @@ impl Formatter {
         }
     }
 
+    /// Write a tag and its value; block content goes at `indent`.
     fn write_tagged(&mut self, tagged: &TaggedValue, indent: usize) -> Result<(), YamlFormatError> {
         let tag_repr = tagged.tag.to_string();
         self.buf.push_str(tag_repr.as_str());
-        self.buf.push(' ');
-        self.write_value(&tagged.value, indent, Position::Inline)
+        match &tagged.value {
+            Value::Mapping(map) if !map.is_empty() => {
+                self.buf.push('\n');
+                self.write_mapping(map, indent)
+            }
+            Value::Sequence(seq) if !seq.is_empty() => {
+                self.buf.push('\n');
+                self.write_sequence(seq, indent)
+            }
+            Value::String(text) if should_use_literal_block(text) => {
+                self.buf.push_str(" |-\n");
+                self.write_literal_block(text, indent);
+                Ok(())
+            }
+            value => {
+                self.buf.push(' ');
+                self.write_value(value, indent, Position::Inline)
+            }
+        }
     }
 
     fn write_key(&mut self, key: &Value) -> Result<(), YamlFormatError> {
@@ -493,6 +937,10 @@ fn should_use_literal_block(text: &str) -> bool {
     if !text.contains('\n') {
         return false;
     }
+    // A carriage return would be read back as part of a line break.
+    if text.contains('\r') {
+        return false;
+    }
     if text.chars().any(is_disallowed_control) {
         return false;
     }
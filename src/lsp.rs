@@ -0,0 +1,310 @@
+//! Language Server Protocol server for format-on-save in editors.
+//!
+//! Speaks JSON-RPC 2.0 with `Content-Length` framing over any reader/writer
+//! pair (stdio in `yars-format lsp`). Documents are synchronised in full and
+//! formatted with the options resolved for their path, exactly as the CLI
+//! would. Parse failures are published as diagnostics; formatting requests
+//! for such documents return no edits.
+//!
+//! Formatting responses are minimal line edits from [`crate::diff`], in
+//! UTF-8 columns when the client offers them and UTF-16 otherwise. Range
+//! formatting reformats only the node enclosing the selection, as
+//! [`crate::format_yaml_range`] does.
+
+use crate::config::ConfigResolver;
+use crate::diff::{text_edits, Position, PositionEncoding, TextEdit};
+use crate::{
+    format_yaml_range_with_options, format_yaml_string_with_options, FormatOptions,
+    YamlFormatError,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serve LSP requests from `input` until the client sends `exit` or closes
+/// the stream. Returns whether `shutdown` was requested first, which decides
+/// the process exit status the protocol expects (0 if so, 1 otherwise).
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<bool> {
+    let mut server = Server::default();
+    while let Some(message) = read_message(&mut input)? {
+        let message = match serde_json::from_slice::<Value>(&message) {
+            Ok(message) => message,
+            Err(err) => {
+                write_message(&mut output, &error_response(Value::Null, PARSE_ERROR, &err.to_string()))?;
+                continue;
+            }
+        };
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(server.shutdown)
+}
+
+#[derive(Debug, Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    encoding: PositionEncoding,
+    shutdown: bool,
+    exited: bool,
+}
+
+impl Server {
+    /// Messages to send in reply to `message`: a response for requests plus
+    /// any notifications it triggers.
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to a server-initiated request; none are sent.
+            return Vec::new();
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        match message.get("id") {
+            Some(id) => {
+                let reply = match self.request(method, &params) {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, text)) => error_response(id.clone(), code, &text),
+                };
+                vec![reply]
+            }
+            None => self.notification(method, &params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if self.shutdown {
+            return Err((INVALID_REQUEST, "server is shutting down".to_owned()));
+        }
+        match method {
+            "initialize" => {
+                let offered = &params["capabilities"]["general"]["positionEncodings"];
+                if offered
+                    .as_array()
+                    .is_some_and(|encodings| encodings.iter().any(|encoding| encoding == "utf-8"))
+                {
+                    self.encoding = PositionEncoding::Utf8;
+                }
+                Ok(json!({
+                    "capabilities": {
+                        "positionEncoding": match self.encoding {
+                            PositionEncoding::Utf8 => "utf-8",
+                            PositionEncoding::Utf16 => "utf-16",
+                        },
+                        "textDocumentSync": {"openClose": true, "change": 1},
+                        "documentFormattingProvider": true,
+                        "documentRangeFormattingProvider": true,
+                    },
+                    "serverInfo": {"name": "yars-format", "version": env!("CARGO_PKG_VERSION")},
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" | "textDocument/rangeFormatting" => {
+                let uri = document_uri(params)
+                    .ok_or_else(|| (INVALID_PARAMS, "missing textDocument.uri".to_owned()))?;
+                let text = self
+                    .documents
+                    .get(uri)
+                    .ok_or_else(|| (INVALID_PARAMS, format!("document {uri} is not open")))?;
+                let selection = match method {
+                    "textDocument/rangeFormatting" => {
+                        let offset = |end: &str| {
+                            let position = &params["range"][end];
+                            Some(
+                                Position {
+                                    line: position["line"].as_u64()? as usize,
+                                    column: position["character"].as_u64()? as usize,
+                                }
+                                .offset(text, self.encoding),
+                            )
+                        };
+                        let start = offset("start")
+                            .ok_or_else(|| (INVALID_PARAMS, "missing range".to_owned()))?;
+                        let end = offset("end")
+                            .ok_or_else(|| (INVALID_PARAMS, "missing range".to_owned()))?;
+                        Some(start..end)
+                    }
+                    _ => None,
+                };
+                Ok(match format_document(uri, text, selection) {
+                    Ok(formatted) => text_edits(text, &formatted, self.encoding)
+                        .iter()
+                        .map(edit_to_json)
+                        .collect(),
+                    Err(_) => Value::Null,
+                })
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method {method}"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        if method == "exit" {
+            self.exited = true;
+            return Vec::new();
+        }
+        let Some(uri) = document_uri(params).map(str::to_owned) else {
+            return Vec::new();
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_owned());
+            }
+            "textDocument/didChange" => {
+                // Full synchronisation: the last change holds the whole text.
+                let Some(text) = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.clone(), text.to_owned());
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish_diagnostics(&uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        }
+
+        let text = &self.documents[&uri];
+        let diagnostics = match format_document(&uri, text, None) {
+            Ok(_) => Vec::new(),
+            Err(err) => vec![diagnostic(text, &err, self.encoding)],
+        };
+        vec![publish_diagnostics(&uri, diagnostics)]
+    }
+}
+
+fn format_document(
+    uri: &str,
+    text: &str,
+    selection: Option<std::ops::Range<usize>>,
+) -> Result<String, YamlFormatError> {
+    // A fresh resolver per request picks up configuration edited while the
+    // editor is running.
+    let options = match uri_to_path(uri) {
+        Some(path) => ConfigResolver::new().resolve(&path)?.format_options()?,
+        None => FormatOptions::default(),
+    };
+    match selection {
+        Some(selection) => format_yaml_range_with_options(text, selection, &options),
+        None => format_yaml_string_with_options(text, &options),
+    }
+}
+
+fn diagnostic(text: &str, err: &YamlFormatError, encoding: PositionEncoding) -> Value {
+    let (line, column) = err
+        .location()
+        .map_or((0, 0), |(line, column)| (line - 1, column - 1));
+    let content = text.split('\n').nth(line).unwrap_or_default().trim_end_matches('\r');
+    let units = |chars: &str| match encoding {
+        PositionEncoding::Utf8 => chars.len(),
+        PositionEncoding::Utf16 => chars.encode_utf16().count(),
+    };
+    // The parser counts columns in characters.
+    let prefix: usize = content.chars().take(column).map(char::len_utf8).sum();
+    json!({
+        "range": {
+            "start": position(line, units(&content[..prefix])),
+            "end": position(line, units(content)),
+        },
+        "severity": 1,
+        "source": "yars",
+        "code": err.code(),
+        "message": err.to_string(),
+    })
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+/// Local path named by a `file:` URI.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    let mut bytes = Vec::with_capacity(rest.len());
+    let mut iter = rest.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn position(line: usize, character: usize) -> Value {
+    json!({"line": line, "character": character})
+}
+
+fn edit_to_json(edit: &TextEdit) -> Value {
+    json!({
+        "range": {
+            "start": position(edit.range.start.line, edit.range.start.column),
+            "end": position(edit.range.end.line, edit.range.end.column),
+        },
+        "newText": edit.new_text,
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// Read one framed message body, or `None` at end of input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let line = header.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
@@ -0,0 +1,262 @@
+//! Three-way structural merge of YAML documents, used by the git merge driver.
+//!
+//! Because formatting makes key order canonical, textual merges of UMF files
+//! mostly conflict on adjacency. Merging the parsed trees instead lets each
+//! mapping key merge independently, so only genuine disagreements conflict:
+//! the same key (or sequence item) changed differently on both sides, or
+//! changed on one side and deleted on the other.
+//!
+//! Sequences of equal length merge item by item. Otherwise, when every item
+//! is a mapping carrying a unique value for one of [`IDENTITY_KEYS`], items
+//! are matched by that value; any other sequence must be changed on at most
+//! one side.
+
+use crate::scan::push_key;
+use crate::{duplicates, emit_yaml, sort_value, strip_leading_marker, tags, FormatOptions, YamlFormatError};
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Keys tried, in order, to identify sequence items across versions.
+pub const IDENTITY_KEYS: &[&str] = &["id", "name", "key"];
+
+/// A location where both sides changed the document incompatibly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// Path to the conflicting node, e.g. `column.nullable.MD` or `validations[2]`.
+    pub path: String,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("conflicting changes to the document root")
+        } else {
+            write!(f, "conflicting changes to {}", self.path)
+        }
+    }
+}
+
+/// Result of merging three document versions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeOutcome {
+    /// The formatted merge result.
+    Merged(String),
+    /// The versions could not be merged structurally.
+    Conflicted(Vec<MergeConflict>),
+}
+
+/// Merge the `ours` and `theirs` revisions of a document descended from
+/// `base`, returning formatted text on success. An empty `base` means the
+/// file was added on both sides.
+pub fn merge_yaml_strings(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    options: &FormatOptions,
+) -> Result<MergeOutcome, YamlFormatError> {
+    let options = options.for_input(ours);
+    let source = ours;
+    let [base, ours, theirs] = [base, ours, theirs].map(|text| parse_document(text, &options));
+    let (base, ours, theirs) = (base?, ours?, theirs?);
+
+    let mut conflicts = Vec::new();
+    let merged = merge_optional(
+        base.as_ref(),
+        ours.as_ref(),
+        theirs.as_ref(),
+        &mut String::new(),
+        &mut conflicts,
+    );
+    if !conflicts.is_empty() {
+        return Ok(MergeOutcome::Conflicted(conflicts));
+    }
+
+    match merged {
+        None => Ok(MergeOutcome::Merged(String::new())),
+        Some(Value::Sequence(_)) => Err(YamlFormatError::top_level_list(source)),
+        Some(value) => emit_yaml(&sort_value(value), &options, source).map(MergeOutcome::Merged),
+    }
+}
+
+fn parse_document(text: &str, options: &FormatOptions) -> Result<Option<Value>, YamlFormatError> {
+    let prepared = tags::prepare(text);
+    match duplicates::parse(&strip_leading_marker(&prepared), options.duplicate_keys, text)? {
+        Value::Null => Ok(None),
+        value => Ok(Some(value)),
+    }
+}
+
+/// Merge a node that may be absent in any version. `None` in the result
+/// means the node is deleted.
+fn merge_optional(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &mut String,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Option<Value> {
+    if ours == theirs {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    if theirs == base {
+        return ours.cloned();
+    }
+
+    match (ours, theirs) {
+        (Some(ours), Some(theirs)) => Some(merge_present(base, ours, theirs, path, conflicts)),
+        // Modified on one side, deleted on the other.
+        _ => {
+            conflicts.push(MergeConflict { path: path.clone() });
+            ours.cloned()
+        }
+    }
+}
+
+/// Merge a node both sides kept but changed differently.
+fn merge_present(
+    base: Option<&Value>,
+    ours: &Value,
+    theirs: &Value,
+    path: &mut String,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Value {
+    match (ours, theirs) {
+        (Value::Mapping(ours), Value::Mapping(theirs)) => {
+            let base = match base {
+                Some(Value::Mapping(base)) => Some(base),
+                _ => None,
+            };
+            Value::Mapping(merge_mappings(base, ours, theirs, path, conflicts))
+        }
+        (Value::Sequence(ours), Value::Sequence(theirs)) => {
+            let base = match base {
+                Some(Value::Sequence(base)) => Some(base.as_slice()),
+                _ => None,
+            };
+            match merge_sequences(base, ours, theirs, path, conflicts) {
+                Some(merged) => Value::Sequence(merged),
+                None => {
+                    conflicts.push(MergeConflict { path: path.clone() });
+                    Value::Sequence(ours.clone())
+                }
+            }
+        }
+        _ => {
+            conflicts.push(MergeConflict { path: path.clone() });
+            ours.clone()
+        }
+    }
+}
+
+fn merge_mappings(
+    base: Option<&Mapping>,
+    ours: &Mapping,
+    theirs: &Mapping,
+    path: &mut String,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Mapping {
+    let mut keys: Vec<&Value> = ours.keys().collect();
+    keys.extend(theirs.keys().filter(|key| !ours.contains_key(*key)));
+    if let Some(base) = base {
+        keys.extend(
+            base.keys()
+                .filter(|key| !ours.contains_key(*key) && !theirs.contains_key(*key)),
+        );
+    }
+
+    let mut merged = Mapping::with_capacity(keys.len());
+    for key in keys {
+        let saved = path.len();
+        push_key(path, key);
+        let value = merge_optional(
+            base.and_then(|base| base.get(key)),
+            ours.get(key),
+            theirs.get(key),
+            path,
+            conflicts,
+        );
+        path.truncate(saved);
+        if let Some(value) = value {
+            merged.insert(key.clone(), value);
+        }
+    }
+    merged
+}
+
+/// Merge two edited sequences, or `None` when they cannot be aligned.
+fn merge_sequences(
+    base: Option<&[Value]>,
+    ours: &[Value],
+    theirs: &[Value],
+    path: &mut String,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Option<Vec<Value>> {
+    let base = base.unwrap_or_default();
+    if ours.len() == theirs.len() && (base.len() == ours.len() || base.is_empty()) {
+        let mut merged = Vec::with_capacity(ours.len());
+        for (idx, (our_item, their_item)) in ours.iter().zip(theirs).enumerate() {
+            let saved = path.len();
+            path.push_str(&format!("[{idx}]"));
+            merged.extend(merge_optional(
+                base.get(idx),
+                Some(our_item),
+                Some(their_item),
+                path,
+                conflicts,
+            ));
+            path.truncate(saved);
+        }
+        return Some(merged);
+    }
+
+    let key = IDENTITY_KEYS
+        .iter()
+        .copied()
+        .find(|key| [base, ours, theirs].iter().all(|items| has_unique_identity(items, key)))?;
+    let identity = |item: &Value| item.get(key).cloned();
+    let find = |items: &[Value], id: &Value| items.iter().find(|item| identity(item).as_ref() == Some(id)).cloned();
+
+    // Keep our order, then append items only they added.
+    let mut order: Vec<Value> = ours.iter().filter_map(identity).collect();
+    let added: Vec<Value> = theirs
+        .iter()
+        .filter_map(identity)
+        .filter(|id| !order.contains(id))
+        .collect();
+    order.extend(added);
+
+    let mut merged = Vec::with_capacity(order.len());
+    for id in order {
+        let saved = path.len();
+        path.push('[');
+        push_key(path, &Value::String(key.to_owned()));
+        path.push('=');
+        push_key(path, &id);
+        path.push(']');
+        let (base_item, our_item, their_item) = (find(base, &id), find(ours, &id), find(theirs, &id));
+        let value = merge_optional(
+            base_item.as_ref(),
+            our_item.as_ref(),
+            their_item.as_ref(),
+            path,
+            conflicts,
+        );
+        path.truncate(saved);
+        merged.extend(value);
+    }
+    Some(merged)
+}
+
+fn has_unique_identity(items: &[Value], key: &str) -> bool {
+    let mut seen = HashSet::new();
+    items.iter().all(|item| match item.get(key) {
+        Some(id @ (Value::String(_) | Value::Number(_) | Value::Bool(_))) => {
+            seen.insert(serde_yaml::to_string(id).unwrap_or_default())
+        }
+        _ => false,
+    })
+}
@@ -0,0 +1,207 @@
+//! Formatting of the single block-style node enclosing a selection.
+//!
+//! The document is scanned line by line for the places where mapping entries
+//! (`key:`) and sequence items (`- `) begin; a node extends over the
+//! following lines indented deeper than it, plus an indentless sequence
+//! directly under a key. The innermost node containing the selection is
+//! re-emitted at its original column and everything outside it is left
+//! byte-identical. A selection enclosed by no node formats the whole document.
+
+use crate::document::Frame;
+use crate::scan::{is_block_scalar_header, node_starts, Line, NodeKind};
+use crate::{
+    duplicates, sort_value, strip_leading_marker, tags, DuplicateKeys, FormatOptions, Formatter,
+    YamlFormatError,
+};
+use serde_yaml::Value;
+use std::ops::Range;
+
+/// Reformat only the mapping entry or sequence item enclosing the byte
+/// `range` of `input`, using default options.
+pub fn format_yaml_range(input: &str, range: Range<usize>) -> Result<String, YamlFormatError> {
+    format_yaml_range_with_options(input, range, &FormatOptions::default())
+}
+
+/// Reformat only the mapping entry or sequence item enclosing the byte
+/// `range` of `input`. Out-of-bounds offsets are clamped to the input.
+pub fn format_yaml_range_with_options(
+    input: &str,
+    range: Range<usize>,
+    options: &FormatOptions,
+) -> Result<String, YamlFormatError> {
+    let original = match parse(input, options.duplicate_keys)? {
+        Value::Sequence(_) => return Err(YamlFormatError::top_level_list(input)),
+        value => value,
+    };
+    let options = &options.for_input(input);
+    let start = range.start.min(input.len());
+    let selection = start..range.end.clamp(start, input.len());
+
+    let lines = Line::split(input);
+    let Some(node) = enclosing_node(&lines, &selection) else {
+        return crate::format_yaml_string_with_options(input, options);
+    };
+
+    let replaced = node.span(&lines);
+    let emitted = emit_node(&node.text(input, &lines), &node, options)?;
+    let indentation = " ".repeat(node.column);
+    let mut reindented = String::with_capacity(emitted.len());
+    for (idx, line) in emitted.split_inclusive('\n').enumerate() {
+        if idx > 0 && !line.trim_end_matches(['\r', '\n']).is_empty() {
+            reindented.push_str(&indentation);
+        }
+        reindented.push_str(line);
+    }
+
+    let mut output = String::with_capacity(input.len());
+    output.push_str(&input[..replaced.start]);
+    output.push_str(&reindented);
+    output.push_str(&input[replaced.end..]);
+
+    // The line scan is heuristic (multi-line flow collections and quoted
+    // scalars can mislead it), so refuse any result that changes meaning.
+    match parse(&output, options.duplicate_keys) {
+        Ok(value) if value == original => Ok(output),
+        _ => Err(node.isolation_error()),
+    }
+}
+
+/// Parse `text` the way [`crate::format_yaml_string_with_options`] does.
+fn parse(text: &str, policy: DuplicateKeys) -> Result<Value, YamlFormatError> {
+    duplicates::parse(&strip_leading_marker(&tags::prepare(text)), policy, text)
+}
+
+/// A node starting at `column` of line `line` and ending with line `last`.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    kind: NodeKind,
+    line: usize,
+    column: usize,
+    last: usize,
+}
+
+impl Node {
+    /// Bytes replaced when the node is reformatted: from its first character
+    /// to the end of its last line, excluding the terminator.
+    fn span(&self, lines: &[Line]) -> Range<usize> {
+        lines[self.line].start + self.column..lines[self.last].content_end
+    }
+
+    /// The node's source alone, at the line and column where it stands in
+    /// `input`, under the document's directives so that tag handles resolve.
+    fn text(&self, input: &str, lines: &[Line]) -> String {
+        let directives = Frame::scan(input).directives;
+        let mut text = String::new();
+        let mut padding = self.line;
+        if !directives.is_empty() {
+            for directive in &directives {
+                text.push_str(directive);
+                text.push('\n');
+            }
+            text.push_str("---\n");
+            padding = padding.saturating_sub(directives.len() + 1);
+        }
+        text.push_str(&"\n".repeat(padding));
+        text.push_str(&" ".repeat(self.column));
+        text.push_str(&input[lines[self.line].start + self.column..lines[self.last].end]);
+        text
+    }
+
+    fn isolation_error(&self) -> YamlFormatError {
+        YamlFormatError::Format(format!(
+            "cannot reformat the node at line {} in isolation",
+            self.line + 1
+        ))
+    }
+}
+
+fn enclosing_node(lines: &[Line], selection: &Range<usize>) -> Option<Node> {
+    // Overlapping block nodes are nested, so the last match is the innermost.
+    let mut innermost = None;
+    // Block scalar content continues while lines are indented past this column.
+    let mut scalar_parent: Option<usize> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        if let Some(parent) = scalar_parent {
+            if line.content.is_empty() || line.indent > parent {
+                continue;
+            }
+            scalar_parent = None;
+        }
+        if line.is_blank_or_comment() {
+            continue;
+        }
+
+        let starts = node_starts(line);
+        if let Some(&(column, _, rest)) = starts.last() {
+            if is_block_scalar_header(rest) {
+                scalar_parent = Some(column);
+            }
+        } else if is_block_scalar_header(line.content) {
+            scalar_parent = Some(line.indent);
+        }
+
+        for (column, kind, _) in starts {
+            let last = last_line(lines, idx, column, kind);
+            let node = Node {
+                kind,
+                line: idx,
+                column,
+                last,
+            };
+            // Whole-line selections start before the indentation.
+            let begin = if column == line.indent {
+                line.start
+            } else {
+                line.start + column
+            };
+            if begin <= selection.start && selection.end <= lines[last].end {
+                innermost = Some(node);
+            }
+        }
+    }
+    innermost
+}
+
+/// Index of the last line belonging to the node starting at `column` of
+/// line `first`. Trailing blank lines and comments are left outside.
+fn last_line(lines: &[Line], first: usize, column: usize, kind: NodeKind) -> usize {
+    let mut last = first;
+    for (idx, line) in lines.iter().enumerate().skip(first + 1) {
+        if line.content.is_empty() {
+            continue;
+        }
+        let indentless_item = kind == NodeKind::Entry
+            && line.indent == column
+            && (line.content == "-" || line.content.starts_with("- "));
+        if line.indent > column || indentless_item {
+            last = idx;
+        } else if !line.content.starts_with('#') {
+            break;
+        }
+    }
+    last
+}
+
+/// Emit a node's source in canonical form, starting at column zero and
+/// without a trailing line terminator.
+fn emit_node(text: &str, node: &Node, options: &FormatOptions) -> Result<String, YamlFormatError> {
+    let options = FormatOptions {
+        final_newline: false,
+        ..options.clone()
+    };
+    let mut formatter = Formatter::new(&options);
+    let value = match duplicates::parse(&tags::prepare(text), options.duplicate_keys, text) {
+        Ok(value) => value,
+        Err(err @ YamlFormatError::DuplicateKeys { .. }) => return Err(err),
+        // The whole document parsed, so the node failed only once cut out of
+        // it, e.g. through an alias to an anchor outside it.
+        Err(_) => return Err(node.isolation_error()),
+    };
+    match (node.kind, sort_value(value)) {
+        (NodeKind::Entry, Value::Mapping(entry)) => formatter.write_mapping(&entry, 0)?,
+        (NodeKind::Item, Value::Sequence(items)) => formatter.write_sequence(&items, 0)?,
+        _ => return Err(YamlFormatError::Format("the selected node is not a block entry or item".to_owned())),
+    }
+    Ok(formatter.finish())
+}
@@ -0,0 +1,605 @@
+//! Per-file results and the reporters that render them.
+//!
+//! A run feeds one [`FileRecord`] per input to a [`Reporter`] as files are
+//! processed, then calls [`Reporter::finish`] with the [`Summary`].
+//!
+//! Besides prose and JSON, results can be rendered for code-scanning and test
+//! dashboards as SARIF, Checkstyle XML or JUnit XML. Those formats report
+//! violations: unformatted files in check mode and files that failed.
+
+use crate::ErrorCode;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::Path;
+
+const TOOL_NAME: &str = "yars-format";
+const TOOL_URI: &str = "https://github.com/easel/yars";
+/// SARIF base URI id for the directory yars ran in, which relative paths
+/// are resolved against.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Outcome of formatting or checking one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileStatus {
+    Unchanged,
+    Changed,
+    Error,
+}
+
+/// Why a file could not be processed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    /// Machine-readable error kind, see [`YamlFormatError::kind`](crate::YamlFormatError::kind).
+    pub kind: String,
+    /// Stable error code, see [`YamlFormatError::code`](crate::YamlFormatError::code).
+    pub code: ErrorCode,
+    pub message: String,
+    /// 1-based line of the offending input, when known.
+    pub line: Option<usize>,
+    /// 1-based column of the offending input, when known.
+    pub column: Option<usize>,
+    /// The offending line with a caret under the column, see [`render_snippet`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+/// Show `path`'s offending line from `source` with a caret under `column`,
+/// the way compilers do. Both positions are 1-based; `None` if `line` is
+/// out of range.
+pub fn render_snippet(path: &str, source: &str, line: usize, column: usize) -> Option<String> {
+    let text = source.split('\n').nth(line.checked_sub(1)?)?.trim_end_matches('\r');
+    // Keep tabs so the caret lines up however they are displayed.
+    let pad: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(line.to_string().len());
+    Some(format!(
+        "{gutter}--> {path}:{line}:{column}\n{gutter} |\n{line} | {text}\n{gutter} | {pad}^\n"
+    ))
+}
+
+/// Result for a single input file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileRecord {
+    pub path: String,
+    pub status: FileStatus,
+    /// Lines that differ between the input and the formatted output.
+    pub lines_changed: usize,
+    /// 1-based line of the first difference, for changed files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_changed_line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorRecord>,
+}
+
+/// Totals for a whole run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// Whether files were only checked rather than rewritten.
+    pub check: bool,
+    pub files: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+    pub errors: usize,
+}
+
+impl Summary {
+    /// Count `record` towards the totals.
+    pub fn add(&mut self, record: &FileRecord) {
+        self.files += 1;
+        match record.status {
+            FileStatus::Unchanged => self.unchanged += 1,
+            FileStatus::Changed => self.changed += 1,
+            FileStatus::Error => self.errors += 1,
+        }
+    }
+}
+
+/// Sink for per-file records and the final summary.
+pub trait Reporter {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()>;
+    fn finish(&mut self, summary: &Summary) -> io::Result<()>;
+}
+
+/// Human-readable prose: optional per-file lines, errors on `err`, and a closing tally.
+pub struct TextReporter<O, E> {
+    verbose: bool,
+    check: bool,
+    out: O,
+    err: E,
+}
+
+impl<O: Write, E: Write> TextReporter<O, E> {
+    pub fn new(verbose: bool, check: bool, out: O, err: E) -> Self {
+        Self {
+            verbose,
+            check,
+            out,
+            err,
+        }
+    }
+}
+
+impl<O: Write, E: Write> Reporter for TextReporter<O, E> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        match (&record.status, &record.error) {
+            (_, Some(error)) => {
+                writeln!(self.err, "Error[{}]: {}: {}", error.code, record.path, error.message)?;
+                match &error.snippet {
+                    Some(snippet) => write!(self.err, "{}", snippet),
+                    None => Ok(()),
+                }
+            }
+            (FileStatus::Changed, None) if self.verbose && self.check => writeln!(
+                self.out,
+                "{} - would reformat ({} differing line(s))",
+                record.path, record.lines_changed
+            ),
+            (FileStatus::Changed, None) if self.verbose => writeln!(
+                self.out,
+                "{} - reformatted ({} line(s) changed)",
+                record.path, record.lines_changed
+            ),
+            (FileStatus::Unchanged, None) if self.verbose => {
+                writeln!(self.out, "{} - already formatted", record.path)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        let processed = summary.files - summary.errors;
+        if summary.check {
+            writeln!(
+                self.out,
+                "Checked {} file(s); {} would change.",
+                processed, summary.changed
+            )?;
+        } else {
+            writeln!(
+                self.out,
+                "Formatted {} file(s); {} updated, {} unchanged.",
+                processed, summary.changed, summary.unchanged
+            )?;
+        }
+        if summary.errors > 0 {
+            writeln!(self.err, "Encountered {} error(s).", summary.errors)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single JSON document `{"files": [...], "summary": {...}}` written on finish.
+pub struct JsonReporter<O> {
+    records: Vec<FileRecord>,
+    out: O,
+}
+
+impl<O: Write> JsonReporter<O> {
+    pub fn new(out: O) -> Self {
+        Self {
+            records: Vec::new(),
+            out,
+        }
+    }
+}
+
+impl<O: Write> Reporter for JsonReporter<O> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        #[derive(Serialize)]
+        struct Document<'a> {
+            files: &'a [FileRecord],
+            summary: &'a Summary,
+        }
+
+        serde_json::to_writer(
+            &mut self.out,
+            &Document {
+                files: &self.records,
+                summary,
+            },
+        )?;
+        writeln!(self.out)
+    }
+}
+
+/// One JSON object per line, streamed as files complete; each carries a
+/// `"type"` of `"file"` or `"summary"`.
+pub struct JsonLinesReporter<O> {
+    out: O,
+}
+
+impl<O: Write> JsonLinesReporter<O> {
+    pub fn new(out: O) -> Self {
+        Self { out }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonLine<'a> {
+    File(&'a FileRecord),
+    Summary(&'a Summary),
+}
+
+impl<O: Write> Reporter for JsonLinesReporter<O> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &JsonLine::File(record))?;
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        serde_json::to_writer(&mut self.out, &JsonLine::Summary(summary))?;
+        writeln!(self.out)
+    }
+}
+
+/// A problem worth surfacing to a code-review or CI tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// `"unformatted"` or the error kind.
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// How serious a [`Violation`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl FileRecord {
+    /// The violation this record represents. Changed files only count in
+    /// check mode; otherwise they were rewritten and need no attention.
+    pub fn violation(&self, check: bool) -> Option<Violation> {
+        match (&self.status, &self.error) {
+            (_, Some(error)) => Some(Violation {
+                rule: error.kind.clone(),
+                severity: Severity::Error,
+                message: error.message.clone(),
+                line: error.line,
+                column: error.column,
+            }),
+            (FileStatus::Changed, None) if check => Some(Violation {
+                rule: "unformatted".to_owned(),
+                severity: Severity::Warning,
+                message: format!(
+                    "File is not formatted ({} differing line(s)); run yars-format to fix",
+                    self.lines_changed
+                ),
+                line: self.first_changed_line,
+                column: None,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// SARIF 2.1.0 log with one run and one result per violation.
+pub struct SarifReporter<O> {
+    check: bool,
+    records: Vec<FileRecord>,
+    out: O,
+}
+
+impl<O: Write> SarifReporter<O> {
+    pub fn new(check: bool, out: O) -> Self {
+        Self {
+            check,
+            records: Vec::new(),
+            out,
+        }
+    }
+}
+
+impl<O: Write> Reporter for SarifReporter<O> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> io::Result<()> {
+        let mut rules: Vec<String> = Vec::new();
+        let mut results = Vec::new();
+        for record in &self.records {
+            let Some(violation) = record.violation(self.check) else {
+                continue;
+            };
+            if !rules.contains(&violation.rule) {
+                rules.push(violation.rule.clone());
+            }
+
+            let mut physical = json!({ "artifactLocation": artifact_location(&record.path) });
+            if let Some(line) = violation.line {
+                let mut region = json!({ "startLine": line });
+                if let Some(column) = violation.column {
+                    region["startColumn"] = json!(column);
+                }
+                physical["region"] = region;
+            }
+            results.push(json!({
+                "ruleId": violation.rule,
+                "level": violation.severity.as_str(),
+                "message": { "text": violation.message },
+                "locations": [{ "physicalLocation": physical }],
+            }));
+        }
+
+        let rules: Vec<_> = rules.iter().map(|rule| json!({ "id": rule })).collect();
+        let mut run = json!({
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": TOOL_URI,
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        });
+        if let Ok(root) = std::env::current_dir() {
+            run["originalUriBaseIds"] = json!({ SRCROOT: { "uri": format!("{}/", file_uri(&root)) } });
+        }
+        let log = json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [run],
+        });
+        serde_json::to_writer_pretty(&mut self.out, &log)?;
+        writeln!(self.out)
+    }
+}
+
+/// `artifactLocation` of `path`: a `file` URI when absolute, otherwise a
+/// relative reference resolved against [`SRCROOT`].
+fn artifact_location(path: &str) -> serde_json::Value {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        json!({ "uri": file_uri(path) })
+    } else {
+        let reference = percent_encode(&path.to_string_lossy().replace('\\', "/"), false);
+        json!({ "uri": reference, "uriBaseId": SRCROOT })
+    }
+}
+
+/// `file` URI of the absolute `path`.
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    // Windows paths (`C:/...`) need a slash before the drive.
+    let slash = if path.starts_with('/') { "" } else { "/" };
+    format!("file://{slash}{}", percent_encode(&path, true))
+}
+
+/// `text` with every byte outside the URI path characters percent-encoded;
+/// `:` is kept only when `colon` is set, since in a relative reference it
+/// could be taken for a scheme.
+fn percent_encode(text: &str, colon: bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(char::from(byte))
+            }
+            b':' if colon => encoded.push(':'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Checkstyle XML with a `<file>` element per input.
+pub struct CheckstyleReporter<O> {
+    check: bool,
+    records: Vec<FileRecord>,
+    out: O,
+}
+
+impl<O: Write> CheckstyleReporter<O> {
+    pub fn new(check: bool, out: O) -> Self {
+        Self {
+            check,
+            records: Vec::new(),
+            out,
+        }
+    }
+}
+
+impl<O: Write> Reporter for CheckstyleReporter<O> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> io::Result<()> {
+        writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(self.out, r#"<checkstyle version="4.3">"#)?;
+        for record in &self.records {
+            let Some(violation) = record.violation(self.check) else {
+                writeln!(self.out, r#"  <file name="{}"/>"#, xml_escape(&record.path))?;
+                continue;
+            };
+            writeln!(self.out, r#"  <file name="{}">"#, xml_escape(&record.path))?;
+            write!(self.out, r#"    <error line="{}""#, violation.line.unwrap_or(1))?;
+            if let Some(column) = violation.column {
+                write!(self.out, r#" column="{}""#, column)?;
+            }
+            writeln!(
+                self.out,
+                r#" severity="{}" message="{}" source="yars.{}"/>"#,
+                violation.severity.as_str(),
+                xml_escape(&violation.message),
+                xml_escape(&violation.rule)
+            )?;
+            writeln!(self.out, "  </file>")?;
+        }
+        writeln!(self.out, "</checkstyle>")
+    }
+}
+
+/// JUnit XML with one test case per input; violations become failures
+/// (unformatted) or errors (processing failures).
+pub struct JunitReporter<O> {
+    check: bool,
+    records: Vec<FileRecord>,
+    out: O,
+}
+
+impl<O: Write> JunitReporter<O> {
+    pub fn new(check: bool, out: O) -> Self {
+        Self {
+            check,
+            records: Vec::new(),
+            out,
+        }
+    }
+}
+
+impl<O: Write> Reporter for JunitReporter<O> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        let violations: Vec<_> = self
+            .records
+            .iter()
+            .map(|record| record.violation(self.check))
+            .collect();
+        let failures = violations
+            .iter()
+            .flatten()
+            .filter(|violation| violation.severity == Severity::Warning)
+            .count();
+        let errors = violations.iter().flatten().count() - failures;
+        let counts = format!(
+            r#"tests="{}" failures="{}" errors="{}""#,
+            summary.files, failures, errors
+        );
+
+        writeln!(self.out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(self.out, r#"<testsuites name="{TOOL_NAME}" {counts}>"#)?;
+        writeln!(self.out, r#"  <testsuite name="{TOOL_NAME}" {counts} skipped="0">"#)?;
+        for (record, violation) in self.records.iter().zip(&violations) {
+            let name = xml_escape(&record.path);
+            let Some(violation) = violation else {
+                writeln!(self.out, r#"    <testcase name="{name}" classname="{TOOL_NAME}"/>"#)?;
+                continue;
+            };
+            let element = match violation.severity {
+                Severity::Warning => "failure",
+                Severity::Error => "error",
+            };
+            let location = match (violation.line, violation.column) {
+                (Some(line), Some(column)) => format!("{}:{}:{}", record.path, line, column),
+                (Some(line), None) => format!("{}:{}", record.path, line),
+                _ => record.path.clone(),
+            };
+            writeln!(self.out, r#"    <testcase name="{name}" classname="{TOOL_NAME}">"#)?;
+            writeln!(
+                self.out,
+                r#"      <{element} type="{}" message="{}">{}: {}</{element}>"#,
+                xml_escape(&violation.rule),
+                xml_escape(&violation.message),
+                xml_escape(&location),
+                xml_escape(&violation.message)
+            )?;
+            writeln!(self.out, "    </testcase>")?;
+        }
+        writeln!(self.out, "  </testsuite>")?;
+        writeln!(self.out, "</testsuites>")
+    }
+}
+
+/// Text output plus GitHub Actions workflow commands (`::error` /
+/// `::warning`), so violations are also annotated inline on pull requests.
+pub struct GithubReporter<O, E> {
+    text: TextReporter<O, E>,
+}
+
+impl<O: Write, E: Write> GithubReporter<O, E> {
+    pub fn new(verbose: bool, check: bool, out: O, err: E) -> Self {
+        Self {
+            text: TextReporter::new(verbose, check, out, err),
+        }
+    }
+}
+
+impl<O: Write, E: Write> Reporter for GithubReporter<O, E> {
+    fn report_file(&mut self, record: &FileRecord) -> io::Result<()> {
+        self.text.report_file(record)?;
+        let Some(violation) = record.violation(self.text.check) else {
+            return Ok(());
+        };
+        let mut properties = format!("file={}", github_property(&record.path));
+        if let Some(line) = violation.line {
+            properties.push_str(&format!(",line={line}"));
+        }
+        if let Some(column) = violation.column {
+            properties.push_str(&format!(",col={column}"));
+        }
+        writeln!(
+            self.text.out,
+            "::{} {},title={}::{}",
+            violation.severity.as_str(),
+            properties,
+            github_property(&format!("{TOOL_NAME} ({})", violation.rule)),
+            github_data(&violation.message)
+        )
+    }
+
+    fn finish(&mut self, summary: &Summary) -> io::Result<()> {
+        self.text.finish(summary)
+    }
+}
+
+fn github_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn github_property(text: &str) -> String {
+    github_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            // XML 1.0 cannot carry other C0 controls, even escaped.
+            ch if ch < '\u{20}' && ch != '\t' && ch != '\r' => escaped.push('\u{fffd}'),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,126 @@
+//! Line-level scanning of block-style YAML.
+//!
+//! The parser does not report where nodes are, so the places where mapping
+//! entries (`key:`) and sequence items (`- `) begin are found by reading the
+//! source a line at a time. The scan is heuristic: flow collections and
+//! multi-line quoted scalars are not followed. Range formatting uses it to
+//! find the node enclosing a selection and duplicate key detection to find
+//! where keys are; both, and the merge driver, name nodes with the dotted
+//! paths [`push_key`] builds.
+
+use serde_yaml::Value;
+
+/// What begins at a node start: a mapping entry (`key:`) or a sequence
+/// item (`- `).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum NodeKind {
+    Entry,
+    Item,
+}
+
+/// One line of the source, with byte offsets into it.
+pub(crate) struct Line<'a> {
+    /// Offset of the first byte.
+    pub start: usize,
+    /// Offset just past the content, before any line terminator.
+    pub content_end: usize,
+    /// Offset just past the line terminator.
+    pub end: usize,
+    /// Number of leading spaces.
+    pub indent: usize,
+    /// The text after the indentation, without trailing whitespace.
+    pub content: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn split(input: &'a str) -> Vec<Line<'a>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for raw in input.split_inclusive('\n') {
+            let body = raw.trim_end_matches(['\r', '\n']);
+            let content = body.trim_start_matches(' ');
+            lines.push(Line {
+                start,
+                content_end: start + body.len(),
+                end: start + raw.len(),
+                indent: body.len() - content.len(),
+                content: content.trim_end(),
+            });
+            start += raw.len();
+        }
+        lines
+    }
+
+    pub fn is_blank_or_comment(&self) -> bool {
+        self.content.is_empty() || self.content.starts_with('#')
+    }
+}
+
+/// Nodes beginning on `line`, outermost first, with the text following
+/// each node's indicator (`- ` or `key:`).
+pub(crate) fn node_starts<'a>(line: &Line<'a>) -> Vec<(usize, NodeKind, &'a str)> {
+    let mut starts = Vec::new();
+    let mut column = line.indent;
+    let mut rest = line.content;
+    while rest == "-" || rest.starts_with("- ") {
+        let after = rest[1..].trim_start_matches(' ');
+        starts.push((column, NodeKind::Item, after));
+        column += rest.len() - after.len();
+        rest = after;
+    }
+    if let Some(value) = mapping_value(rest) {
+        starts.push((column, NodeKind::Entry, value));
+    }
+    starts
+}
+
+/// The text after `key:` when `text` starts with a simple mapping key.
+fn mapping_value(text: &str) -> Option<&str> {
+    let after_key = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let mut escaped = false;
+            let close = text[1..].char_indices().find(|&(_, ch)| {
+                let found = ch == quote && !escaped;
+                escaped = quote == '"' && ch == '\\' && !escaped;
+                found
+            })?;
+            &text[1 + close.0 + 1..]
+        }
+        '#' | '[' | '{' | '?' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(idx, _)| idx)
+                .find(|&idx| text[idx + 1..].is_empty() || text[idx + 1..].starts_with(' '))?;
+            if text[..colon].contains(" #") {
+                return None;
+            }
+            &text[colon..]
+        }
+    };
+    let value = after_key.strip_prefix(':')?;
+    (value.is_empty() || value.starts_with(' ')).then(|| value.trim_start())
+}
+
+/// Whether `value` (text after `key:` or `- `) opens a literal or folded scalar.
+pub(crate) fn is_block_scalar_header(value: &str) -> bool {
+    let value = value.split(" #").next().unwrap_or_default().trim_end();
+    let mut chars = value.chars();
+    matches!(chars.next(), Some('|' | '>'))
+        && chars.all(|ch| ch == '-' || ch == '+' || ch.is_ascii_digit())
+}
+
+/// Append `key` to a dotted path such as `jobs.build[0]`.
+pub(crate) fn push_key(path: &mut String, key: &Value) {
+    if !path.is_empty() && !path.ends_with('[') && !path.ends_with('=') {
+        path.push('.');
+    }
+    match key {
+        Value::String(text) => path.push_str(text),
+        other => path.push_str(
+            serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim_end(),
+        ),
+    }
+}
@@ -0,0 +1,268 @@
+//! `%TAG` directives and tag shorthands.
+//!
+//! The parser resolves every tag to its full name and keeps only local
+//! (`!name`) tags on the value, so global tags would be lost. Before parsing,
+//! each tag that resolves to a global name is rewritten as a verbatim local
+//! tag whose name is the tag's canonical spelling: the handle declared for it
+//! (`!e!widget`, `!!str`) or else the verbatim form
+//! (`!<tag:example.com,2000:widget>`). The emitter prints that spelling as
+//! is, and keeps the `%TAG` directives whose handles it uses.
+
+use crate::document::Frame;
+use serde_yaml::Value;
+use std::borrow::Cow;
+use std::ops::Range;
+
+const CORE_PREFIX: &str = "tag:yaml.org,2002:";
+
+/// Stand-in tag for the check in [`prepare`].
+const PLACEHOLDER: &str = "!yars";
+
+/// `input` with every global tag spelled as a local tag carrying its
+/// canonical shorthand. The rewrite is checked against the input with the
+/// same tags replaced by a fixed local one, so scalars resolve alike in
+/// both, and abandoned if anything but the tags differs.
+pub(crate) fn prepare(input: &str) -> Cow<'_, str> {
+    let handles = Handles::declared(input);
+    let mut rewritten = String::new();
+    let mut baseline = String::new();
+    let mut copied = 0;
+    for span in tag_spans(input) {
+        let Some(name) = handles.resolve(&input[span.clone()]) else {
+            continue;
+        };
+        if name.starts_with('!') {
+            continue;
+        }
+        baseline.push_str(&input[copied..span.start]);
+        baseline.push_str(PLACEHOLDER);
+        rewritten.push_str(&input[copied..span.start]);
+        // The parser and the tag's `Display` each strip a leading `!`.
+        rewritten.push_str("!<!");
+        for ch in handles.canonical(&name).chars() {
+            match ch {
+                '%' => rewritten.push_str("%25"),
+                '<' => rewritten.push_str("%3C"),
+                '>' => rewritten.push_str("%3E"),
+                ch => rewritten.push(ch),
+            }
+        }
+        rewritten.push('>');
+        copied = span.end;
+    }
+    if copied == 0 {
+        return Cow::Borrowed(input);
+    }
+    rewritten.push_str(&input[copied..]);
+    baseline.push_str(&input[copied..]);
+
+    let parse = |text: &str| serde_yaml::from_str::<Value>(text).ok().map(untagged);
+    match (parse(&baseline), parse(&rewritten)) {
+        (Some(original), Some(prepared)) if original == prepared => Cow::Owned(rewritten),
+        _ => Cow::Borrowed(input),
+    }
+}
+
+/// Whether some tag in `value` is spelled with the handle the `%TAG`
+/// directive `directive` declares.
+pub(crate) fn uses_directive(value: &Value, directive: &str) -> bool {
+    let Some(handle) = directive.split_whitespace().nth(1) else {
+        return false;
+    };
+    match value {
+        // Redeclaring the primary handle changes what every `!name` means.
+        Value::Tagged(tagged) => {
+            handle == "!"
+                || tagged.tag.to_string().starts_with(handle)
+                || uses_directive(&tagged.value, directive)
+        }
+        Value::Sequence(items) => items.iter().any(|item| uses_directive(item, directive)),
+        Value::Mapping(map) => map
+            .iter()
+            .any(|(key, value)| uses_directive(key, directive) || uses_directive(value, directive)),
+        _ => false,
+    }
+}
+
+fn untagged(value: Value) -> Value {
+    match value {
+        Value::Tagged(tagged) => untagged(tagged.value),
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(untagged).collect()),
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .map(|(key, value)| (untagged(key), untagged(value)))
+                .collect(),
+        ),
+        scalar => scalar,
+    }
+}
+
+/// Tag handles in scope: `!` and `!!` with their defaults, overridden or
+/// extended by `%TAG` directives.
+struct Handles(Vec<(String, String)>);
+
+impl Handles {
+    fn declared(input: &str) -> Handles {
+        let mut handles = vec![
+            ("!".to_owned(), "!".to_owned()),
+            ("!!".to_owned(), CORE_PREFIX.to_owned()),
+        ];
+        for directive in Frame::scan(input).directives {
+            let mut parts = directive.split_whitespace();
+            if let (Some("%TAG"), Some(handle), Some(prefix)) = (parts.next(), parts.next(), parts.next()) {
+                handles.retain(|(declared, _)| declared != handle);
+                handles.push((handle.to_owned(), prefix.to_owned()));
+            }
+        }
+        Handles(handles)
+    }
+
+    /// Full name of the tag property `token`, or `None` for the
+    /// non-specific tag and undeclared handles.
+    fn resolve(&self, token: &str) -> Option<String> {
+        if let Some(verbatim) = token.strip_prefix("!<") {
+            return verbatim.strip_suffix('>').map(str::to_owned);
+        }
+        let rest = &token[1..];
+        let (handle, suffix) = match rest.find('!') {
+            Some(end) => token.split_at(end + 2),
+            None => ("!", rest),
+        };
+        if suffix.is_empty() {
+            return None;
+        }
+        let (_, prefix) = self.0.iter().find(|(declared, _)| declared == handle)?;
+        Some(format!("{prefix}{suffix}"))
+    }
+
+    /// The spelling of the global tag `name`: the handle with the longest
+    /// matching prefix, or the verbatim form when none matches.
+    fn canonical(&self, name: &str) -> String {
+        self.0
+            .iter()
+            .filter_map(|(handle, prefix)| {
+                let suffix = name.strip_prefix(prefix.as_str())?;
+                let valid = !suffix.is_empty()
+                    && !suffix.contains(['!', ',', '[', ']', '{', '}'])
+                    && !suffix.starts_with(['<', '>']);
+                valid.then_some((prefix.len(), handle, suffix))
+            })
+            .max_by_key(|(len, _, _)| *len)
+            .map_or_else(|| format!("!<{name}>"), |(_, handle, suffix)| format!("{handle}{suffix}"))
+    }
+}
+
+/// Byte ranges of the tag properties in `input`. This is a lightweight scan
+/// that skips directives, comments, quoted scalars and block scalar bodies;
+/// [`prepare`] verifies its result.
+fn tag_spans(input: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut block_scalar: Option<usize> = None;
+    let mut flow_depth = 0usize;
+    let mut offset = 0;
+
+    for line in input.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let body = line.trim_end_matches(['\r', '\n']);
+        let indent = body.len() - body.trim_start_matches(' ').len();
+        if let Some(parent) = block_scalar {
+            if body.trim().is_empty() || indent > parent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if quote.is_none() && body.starts_with('%') {
+            continue;
+        }
+
+        let bytes = body.as_bytes();
+        let mut node_start = true;
+        let mut idx = if quote.is_none() && (body.starts_with("---") || body.starts_with("...")) {
+            3
+        } else {
+            0
+        };
+        while idx < bytes.len() {
+            let ch = bytes[idx];
+            if let Some(open) = quote {
+                match ch {
+                    b'\\' if open == '"' => idx += 1,
+                    b'\'' if open == '\'' && bytes.get(idx + 1) == Some(&b'\'') => idx += 1,
+                    _ if ch as char == open => quote = None,
+                    _ => {}
+                }
+                idx += 1;
+                continue;
+            }
+            let separated = |at: usize| bytes.get(at).is_none_or(|next| matches!(next, b' ' | b'\t'));
+            if ch == b' ' || ch == b'\t' {
+                idx += 1;
+                continue;
+            }
+            if ch == b'#' && (idx == 0 || matches!(bytes[idx - 1], b' ' | b'\t')) {
+                break;
+            }
+            if node_start {
+                match ch {
+                    b'!' | b'&' | b'*' => {
+                        let end = property_end(bytes, idx, flow_depth > 0);
+                        if ch == b'!' {
+                            spans.push(start + idx..start + end);
+                        }
+                        node_start = ch != b'*';
+                        idx = end;
+                        continue;
+                    }
+                    b'"' | b'\'' => {
+                        quote = Some(ch as char);
+                        node_start = false;
+                    }
+                    b'-' | b'?' | b':' if separated(idx + 1) => {}
+                    b'|' | b'>' => {
+                        block_scalar = Some(indent);
+                        break;
+                    }
+                    b'[' | b'{' => flow_depth += 1,
+                    b']' | b'}' => {
+                        flow_depth = flow_depth.saturating_sub(1);
+                        node_start = false;
+                    }
+                    b',' if flow_depth > 0 => {}
+                    _ => node_start = false,
+                }
+            } else {
+                match ch {
+                    b':' if separated(idx + 1)
+                        || (flow_depth > 0 && matches!(bytes.get(idx + 1), Some(b',' | b']' | b'}'))) =>
+                    {
+                        node_start = true
+                    }
+                    b',' if flow_depth > 0 => node_start = true,
+                    b']' | b'}' if flow_depth > 0 => flow_depth -= 1,
+                    _ => {}
+                }
+            }
+            idx += 1;
+        }
+    }
+    spans
+}
+
+/// End of the tag, anchor or alias starting at `start`.
+fn property_end(bytes: &[u8], start: usize, in_flow: bool) -> usize {
+    if bytes[start..].starts_with(b"!<") {
+        if let Some(close) = bytes[start..].iter().position(|&ch| ch == b'>') {
+            return start + close + 1;
+        }
+    }
+    let mut end = start + 1;
+    while end < bytes.len()
+        && !matches!(bytes[end], b' ' | b'\t')
+        && !(in_flow && matches!(bytes[end], b',' | b'[' | b']' | b'{' | b'}'))
+    {
+        end += 1;
+    }
+    end
+}
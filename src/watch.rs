@@ -0,0 +1,190 @@
+//! File-system watching for `--watch`, backed by `notify` (inotify on Linux).
+//!
+//! Files are watched through their parent directory so editors that save by
+//! renaming a temporary file over the original keep being tracked. Bursts of
+//! events are debounced into batches, and a file whose content still matches
+//! the snapshot taken after it was last processed is dropped, which keeps the
+//! formatter's own writes from triggering another pass.
+
+use crate::git::is_yaml_path;
+use crate::YamlFormatError;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Quiet period after the last event before a batch is released.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// YAML files named by `paths`, with directories expanded recursively.
+/// Hidden entries inside directories (`.git`, caches) are skipped.
+pub fn yaml_files(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            collect_yaml_files(path, &mut files);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+}
+
+fn collect_yaml_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| !is_hidden(&entry.file_name()))
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_yaml_files(&path, files);
+        } else if is_yaml_path(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+/// Whether `path` lies under `dir` without passing through a hidden entry,
+/// the files [`yaml_files`] would find there.
+fn is_visible_under(path: &Path, dir: &Path) -> bool {
+    path.strip_prefix(dir).is_ok_and(|relative| !relative.iter().any(is_hidden))
+}
+
+/// Watches files and directories for saved YAML files.
+pub struct Watcher {
+    // Dropping the watcher stops event delivery.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    files: HashSet<PathBuf>,
+    dirs: Vec<PathBuf>,
+    debounce: Duration,
+    settled: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl Watcher {
+    /// Start watching `paths`: directories recursively, files individually.
+    pub fn new(paths: &[PathBuf], debounce: Duration) -> Result<Self, YamlFormatError> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)
+            .map_err(|err| YamlFormatError::Watch(err.to_string()))?;
+
+        let mut files = HashSet::new();
+        let mut dirs = Vec::new();
+        let mut parents = BTreeSet::new();
+        for path in paths {
+            let absolute = std::path::absolute(path)
+                .map_err(|err| YamlFormatError::Watch(format!("{}: {}", path.display(), err)))?;
+            if absolute.is_dir() {
+                watcher
+                    .watch(&absolute, RecursiveMode::Recursive)
+                    .map_err(|err| YamlFormatError::Watch(format!("{}: {}", path.display(), err)))?;
+                dirs.push(absolute);
+            } else {
+                let parent = absolute.parent().unwrap_or(Path::new("/")).to_path_buf();
+                parents.insert(parent);
+                files.insert(absolute);
+            }
+        }
+        for parent in parents {
+            watcher
+                .watch(&parent, RecursiveMode::NonRecursive)
+                .map_err(|err| YamlFormatError::Watch(format!("{}: {}", parent.display(), err)))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            files,
+            dirs,
+            debounce,
+            settled: HashMap::new(),
+        })
+    }
+
+    /// Remember the current content of `path` as processed; events that
+    /// leave it unchanged are ignored.
+    pub fn settle(&mut self, path: &Path) {
+        let Ok(absolute) = std::path::absolute(path) else {
+            return;
+        };
+        match fs::read(&absolute) {
+            Ok(contents) => self.settled.insert(absolute, contents),
+            Err(_) => self.settled.remove(&absolute),
+        };
+    }
+
+    /// Block until watched YAML files change and then stay quiet for the
+    /// debounce period, returning them sorted. With a `timeout`, returns an
+    /// empty batch if nothing changed in time.
+    pub fn next_batch(&mut self, timeout: Option<Duration>) -> Result<Vec<PathBuf>, YamlFormatError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut batch = BTreeSet::new();
+        // Only relevant events extend the quiet period; reads of the watched
+        // files (including by whoever is waiting on them) must not.
+        let mut quiet_until = None;
+        loop {
+            let wait = match (quiet_until, deadline) {
+                (Some(quiet_until), _) => quiet_until,
+                (None, Some(deadline)) => deadline,
+                (None, None) => Instant::now() + Duration::from_secs(3600),
+            }
+            .saturating_duration_since(Instant::now());
+            match self.events.recv_timeout(wait) {
+                Ok(Ok(event)) => {
+                    let relevant: Vec<PathBuf> = self.relevant_paths(event).collect();
+                    if !relevant.is_empty() {
+                        batch.extend(relevant);
+                        quiet_until = Some(Instant::now() + self.debounce);
+                    }
+                }
+                Ok(Err(err)) => return Err(YamlFormatError::Watch(err.to_string())),
+                Err(RecvTimeoutError::Timeout) => {
+                    quiet_until = None;
+                    let changed: Vec<PathBuf> = std::mem::take(&mut batch)
+                        .into_iter()
+                        .filter(|path| self.is_changed(path))
+                        .collect();
+                    if !changed.is_empty() || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Ok(changed);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(YamlFormatError::Watch("event channel closed".to_owned()))
+                }
+            }
+        }
+    }
+
+    fn relevant_paths(&self, event: Event) -> impl Iterator<Item = PathBuf> + '_ {
+        // Access events (opens and reads) never change content.
+        let mutating = matches!(
+            event.kind,
+            EventKind::Any | EventKind::Create(_) | EventKind::Modify(_)
+        );
+        event.paths.into_iter().filter(move |path| {
+            mutating
+                && (self.files.contains(path)
+                    || (is_yaml_path(path) && self.dirs.iter().any(|dir| is_visible_under(path, dir))))
+        })
+    }
+
+    fn is_changed(&self, path: &Path) -> bool {
+        match fs::read(path) {
+            Ok(contents) => self.settled.get(path) != Some(&contents),
+            // Deleted again (or replaced by something unreadable) before the batch closed.
+            Err(_) => path.exists(),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::atomic::{backup_path, read_stamped, write_atomic, WriteError, WriteOptions};
+
+#[test]
+fn replaces_contents_and_leaves_no_temporary_files() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+
+    write_atomic(&spec, b"a: 1\n", &WriteOptions::default(), None).unwrap();
+
+    assert_eq!(fs::read_to_string(&spec).unwrap(), "a: 1\n");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn backup_keeps_previous_contents() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+
+    write_atomic(&spec, b"a: 1\n", &WriteOptions { backup: true }, None).unwrap();
+
+    assert_eq!(backup_path(&spec), dir.path().join("spec.yaml.bak"));
+    assert_eq!(fs::read_to_string(backup_path(&spec)).unwrap(), "b: 1\n");
+    assert_eq!(fs::read_to_string(&spec).unwrap(), "a: 1\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions_are_preserved() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+    fs::set_permissions(&spec, fs::Permissions::from_mode(0o640)).unwrap();
+
+    write_atomic(&spec, b"a: 1\n", &WriteOptions::default(), None).unwrap();
+
+    let mode = fs::metadata(&spec).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o640);
+}
+
+#[cfg(unix)]
+#[test]
+fn new_files_get_the_default_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let reference = dir.path().join("reference.yaml");
+    fs::write(&reference, "").unwrap();
+    let spec = dir.path().join("spec.yaml");
+
+    write_atomic(&spec, b"a: 1\n", &WriteOptions::default(), None).unwrap();
+
+    let mode = |path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&spec), mode(&reference));
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_kept_and_their_target_updated() {
+    let dir = tempdir().unwrap();
+    let target = dir.path().join("real.yaml");
+    let link = dir.path().join("link.yaml");
+    fs::write(&target, "b: 1\n").unwrap();
+    std::os::unix::fs::symlink("real.yaml", &link).unwrap();
+
+    write_atomic(&link, b"a: 1\n", &WriteOptions::default(), None).unwrap();
+
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "a: 1\n");
+}
+
+#[test]
+fn stamped_write_succeeds_when_file_is_untouched() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+
+    let (contents, stamp) = read_stamped(&spec).unwrap();
+    assert_eq!(contents, b"b: 1\n");
+    write_atomic(&spec, b"a: 1\n", &WriteOptions::default(), Some(&stamp)).unwrap();
+
+    assert_eq!(fs::read_to_string(&spec).unwrap(), "a: 1\n");
+}
+
+#[test]
+fn concurrent_modification_is_not_overwritten() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+
+    let (_, stamp) = read_stamped(&spec).unwrap();
+    fs::write(&spec, "c: 3\n").unwrap();
+    let err = write_atomic(&spec, b"a: 1\n", &WriteOptions { backup: true }, Some(&stamp)).unwrap_err();
+
+    assert!(matches!(err, WriteError::Modified));
+    assert_eq!(err.into_error(&spec).kind(), "concurrent_modification");
+    assert_eq!(fs::read_to_string(&spec).unwrap(), "c: 3\n");
+    assert!(!backup_path(&spec).exists());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
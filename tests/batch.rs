@@ -0,0 +1,70 @@
+use std::fs;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::batch::Batch;
+use yars_yaml_formatter::report::FileStatus;
+use yars_yaml_formatter::{FormatOptions, YamlFormatError};
+
+#[test]
+fn reports_every_outcome_in_input_order() {
+    let dir = tempdir().unwrap();
+    let unchanged = dir.path().join("unchanged.yaml");
+    let changed = dir.path().join("changed.yaml");
+    let invalid = dir.path().join("invalid.yaml");
+    let missing = dir.path().join("missing.yaml");
+    fs::write(&unchanged, "a: 1\n").unwrap();
+    fs::write(&changed, "b: 1\na: 2\n").unwrap();
+    fs::write(&invalid, "a: [1\n").unwrap();
+
+    let report = Batch::new(false).run([&unchanged, &changed, &invalid, &missing], &FormatOptions::default());
+
+    let statuses: Vec<FileStatus> = report.files.iter().map(|file| file.status).collect();
+    assert_eq!(
+        statuses,
+        [FileStatus::Unchanged, FileStatus::Changed, FileStatus::Error, FileStatus::Error]
+    );
+    assert_eq!(report.changed(), 1);
+    assert_eq!(report.errors(), 2);
+    assert_eq!(fs::read_to_string(&changed).unwrap(), "a: 2\nb: 1\n");
+    assert_eq!(fs::read_to_string(&unchanged).unwrap(), "a: 1\n");
+    assert!(!missing.exists());
+
+    let [same, rewritten, failed, absent] = &report.files[..] else {
+        panic!("expected four results");
+    };
+    assert_eq!(same.path, unchanged);
+    assert!(same.error.is_none());
+    assert_eq!(same.stats.lines_changed, 0);
+    assert_eq!(rewritten.stats.lines_changed, 2);
+    assert_eq!(rewritten.stats.first_changed_line, Some(1));
+    assert!(matches!(&failed.error, Some(YamlFormatError::Parse { path: Some(path), .. }) if *path == invalid.display().to_string()));
+    assert_eq!(failed.source.as_deref(), Some("a: [1\n"));
+    assert!(matches!(absent.error, Some(YamlFormatError::MissingFile(_))));
+    assert!(absent.source.is_none());
+}
+
+#[test]
+fn check_only_reports_changes_without_writing() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("spec.yaml");
+    fs::write(&file, "b: 1\na: 2\n").unwrap();
+
+    let result = Batch::new(true).format_file(&file, &FormatOptions::default());
+
+    assert_eq!(result.status, FileStatus::Changed);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "b: 1\na: 2\n");
+}
+
+#[test]
+fn formatter_errors_leave_the_file_alone() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("spec.yaml");
+    fs::write(&file, "a: 1\n").unwrap();
+
+    let mut batch = Batch::new(false).formatter(|_, _| Err(YamlFormatError::Format("refused".to_owned())));
+    let result = batch.format_file(&file, &FormatOptions::default());
+
+    assert_eq!(result.status, FileStatus::Error);
+    assert_eq!(result.error.unwrap().to_string(), "Error formatting YAML: refused");
+    assert_eq!(fs::read_to_string(&file).unwrap(), "a: 1\n");
+}
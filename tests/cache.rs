@@ -0,0 +1,84 @@
+use std::fs;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::cache::FormatCache;
+use yars_yaml_formatter::FormatOptions;
+
+#[test]
+fn cached_entry_survives_reload() {
+    let dir = tempdir().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let file = dir.path().join("a.yaml");
+    fs::write(&file, "a: 1\n").unwrap();
+    let options = FormatOptions::default();
+
+    let mut cache = FormatCache::open(&cache_dir);
+    assert!(!cache.is_formatted(&file, "a: 1\n", &options));
+    cache.record_formatted(&file, "a: 1\n", &options);
+    cache.save().unwrap();
+
+    let reloaded = FormatCache::open(&cache_dir);
+    assert!(reloaded.is_formatted(&file, "a: 1\n", &options));
+}
+
+#[test]
+fn content_or_option_changes_invalidate_entry() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("a.yaml");
+    fs::write(&file, "a: 1\n").unwrap();
+    let options = FormatOptions::default();
+
+    let mut cache = FormatCache::open(&dir.path().join("cache"));
+    cache.record_formatted(&file, "a: 1\n", &options);
+
+    let wider = FormatOptions {
+        indent: 4,
+        ..FormatOptions::default()
+    };
+    assert!(!cache.is_formatted(&file, "a: 1\n", &wider));
+
+    fs::write(&file, "a: 2\n").unwrap();
+    assert!(!cache.is_formatted(&file, "a: 2\n", &options));
+}
+
+#[test]
+fn foreign_version_is_discarded() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("a.yaml");
+    fs::write(&file, "a: 1\n").unwrap();
+    let cache_dir = dir.path().join("cache");
+    let options = FormatOptions::default();
+
+    let mut cache = FormatCache::open(&cache_dir);
+    cache.record_formatted(&file, "a: 1\n", &options);
+    cache.save().unwrap();
+
+    let stored = cache_dir.join("formatted.json");
+    let text = fs::read_to_string(&stored).unwrap();
+    let stale = text.replacen(yars_yaml_formatter::cache::FORMATTER_VERSION, "0.0.0-stale", 1);
+    fs::write(&stored, stale).unwrap();
+
+    assert!(!FormatCache::open(&cache_dir).is_formatted(&file, "a: 1\n", &options));
+}
+
+#[test]
+fn other_builds_of_the_same_version_are_discarded() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("a.yaml");
+    fs::write(&file, "a: 1\n").unwrap();
+    let cache_dir = dir.path().join("cache");
+    let options = FormatOptions::default();
+
+    let mut cache = FormatCache::open(&cache_dir);
+    cache.record_formatted(&file, "a: 1\n", &options);
+    cache.save().unwrap();
+
+    let version = yars_yaml_formatter::cache::FORMATTER_VERSION;
+    let (package, _) = version.split_once('+').unwrap();
+    assert_eq!(package, env!("CARGO_PKG_VERSION"));
+    let stored = cache_dir.join("formatted.json");
+    let text = fs::read_to_string(&stored).unwrap();
+    fs::write(&stored, text.replacen(version, &format!("{package}+0000000000000000"), 1)).unwrap();
+
+    assert!(!FormatCache::open(&cache_dir).is_formatted(&file, "a: 1\n", &options));
+}
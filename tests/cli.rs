@@ -1,6 +1,7 @@
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 use tempfile::tempdir;
 
@@ -73,7 +74,7 @@ fn missing_file_reports_error() {
         .assert()
         .failure()
         .code(2)
-        .stderr(predicate::str::contains("Failed to read file"));
+        .stderr(predicate::str::contains("File not found"));
 }
 
 #[test]
@@ -85,3 +86,433 @@ fn generates_shell_completions() {
         .success()
         .stdout(predicate::str::contains("_yars-format"));
 }
+
+#[test]
+fn discovered_config_applies_and_prints() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nindent = 4\n").unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "a:\n  b: 1\n").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("--print-config")
+        .arg(&file_path)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("indent = 4").and(predicate::str::contains(".yars.toml")));
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg(&file_path)
+        .assert()
+        .success();
+    assert_eq!(read_file(&file_path), "a:\n    b: 1\n");
+}
+
+#[test]
+fn explicit_config_bypasses_discovery() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nindent = 4\n").unwrap();
+    let explicit = dir.path().join("explicit.toml");
+    fs::write(&explicit, "indent = 2\n").unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "a:\n  b: 1\n").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("--check")
+        .arg("--config")
+        .arg(&explicit)
+        .arg(&file_path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn cache_dir_is_written_unless_disabled() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "a: 1\n").unwrap();
+    let cache_dir = dir.path().join("cache");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("--no-cache")
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg(&file_path)
+        .assert()
+        .success();
+    assert!(!cache_dir.exists());
+
+    for _ in 0..2 {
+        Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+            .arg("--check")
+            .arg("--cache-dir")
+            .arg(&cache_dir)
+            .arg(&file_path)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Checked 1 file(s); 0 would change."));
+    }
+    assert!(cache_dir.join("formatted.json").exists());
+}
+
+#[test]
+fn json_output_reports_each_file() {
+    let dir = tempdir().unwrap();
+    let changed = dir.path().join("changed.yaml");
+    let broken = dir.path().join("broken.yaml");
+    fs::write(&changed, "b: 1\na: 2\n").unwrap();
+    fs::write(&broken, "foo: [bar\n").unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--output-format", "json"])
+        .arg(&changed)
+        .arg(&broken)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files"][0]["status"], "changed");
+    assert_eq!(report["files"][0]["lines_changed"], 2);
+    assert_eq!(report["files"][1]["status"], "error");
+    assert_eq!(report["files"][1]["error"]["kind"], "parse");
+    assert_eq!(report["summary"]["changed"], 1);
+    assert_eq!(report["summary"]["errors"], 1);
+}
+
+#[test]
+fn parse_errors_show_the_offending_line() {
+    let dir = tempdir().unwrap();
+    let broken = dir.path().join("broken.yaml");
+    fs::write(&broken, "a: 1\n  b: 2\n").unwrap();
+    let shown = broken.display().to_string();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache"])
+        .arg(&broken)
+        .assert()
+        .code(2)
+        .stderr(
+            predicate::str::contains(format!("--> {shown}:2:"))
+                .and(predicate::str::contains("2 |   b: 2\n"))
+                .and(predicate::str::contains("^")),
+        );
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--output-format", "json"])
+        .arg(&broken)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files"][0]["error"]["line"], 2);
+    assert!(report["files"][0]["error"]["snippet"].is_string());
+}
+
+#[test]
+fn top_level_list_errors_show_the_list() {
+    let dir = tempdir().unwrap();
+    let listed = dir.path().join("listed.yaml");
+    fs::write(&listed, "# rules\n- a\n- b\n").unwrap();
+    let shown = listed.display().to_string();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache"])
+        .arg(&listed)
+        .assert()
+        .code(2)
+        .stderr(
+            predicate::str::contains("Top-level lists are not supported")
+                .and(predicate::str::contains(format!("--> {shown}:2:1")))
+                .and(predicate::str::contains("2 | - a\n")),
+        );
+}
+
+#[test]
+fn detailed_exit_codes_distinguish_error_categories() {
+    let dir = tempdir().unwrap();
+    let broken = dir.path().join("broken.yaml");
+    let listed = dir.path().join("listed.yaml");
+    let missing = dir.path().join("missing.yaml");
+    fs::write(&broken, "a: [1\n").unwrap();
+    fs::write(&listed, "- a\n").unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--output-format", "json"])
+        .arg(&broken)
+        .arg(&listed)
+        .arg(&missing)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["files"][0]["error"]["code"], "Y001");
+    assert_eq!(report["files"][1]["error"]["code"], "Y002");
+    assert_eq!(report["files"][2]["error"]["code"], "Y010");
+
+    for (path, code) in [(&broken, 3), (&listed, 4), (&missing, 5)] {
+        Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+            .args(["--check", "--no-cache", "--detailed-exit-codes"])
+            .arg(path)
+            .assert()
+            .code(code);
+    }
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--detailed-exit-codes"])
+        .arg(&broken)
+        .arg(&listed)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Error[Y002]:"));
+}
+
+#[test]
+fn json_lines_output_streams_records() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "a: 1\n").unwrap();
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--output-format", "json-lines"])
+        .arg(&file_path)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["type"], "file");
+    assert_eq!(lines[0]["status"], "unchanged");
+    assert_eq!(lines[1]["type"], "summary");
+    assert_eq!(lines[1]["files"], 1);
+}
+
+#[test]
+fn sarif_reporter_lists_violations() {
+    let dir = tempdir().unwrap();
+    let changed = dir.path().join("needs format.yaml");
+    let clean = dir.path().join("clean.yaml");
+    fs::write(&changed, "a: 1\nc: 3\nb: 2\n").unwrap();
+    fs::write(&clean, "a: 1\n").unwrap();
+
+    let sarif = |paths: &[&Path]| -> serde_json::Value {
+        let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+            .current_dir(dir.path())
+            .args(["--check", "--no-cache", "--reporter", "sarif"])
+            .args(paths)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    let log = sarif(&[Path::new("needs format.yaml"), Path::new("clean.yaml")]);
+    assert_eq!(log["version"], "2.1.0");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["ruleId"], "unformatted");
+    let location = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(location["artifactLocation"]["uri"], "needs%20format.yaml");
+    assert_eq!(location["artifactLocation"]["uriBaseId"], "%SRCROOT%");
+    assert_eq!(location["region"]["startLine"], 2);
+    let root = log["runs"][0]["originalUriBaseIds"]["%SRCROOT%"]["uri"].as_str().unwrap();
+    assert!(root.starts_with("file:///") && root.ends_with('/'));
+
+    let log = sarif(&[&changed, &clean]);
+    let location = &log["runs"][0]["results"][0]["locations"][0]["physicalLocation"];
+    let uri = location["artifactLocation"]["uri"].as_str().unwrap();
+    assert!(uri.starts_with("file:///") && uri.ends_with("/needs%20format.yaml"), "{uri}");
+    assert!(location["artifactLocation"].get("uriBaseId").is_none());
+}
+
+#[test]
+fn checkstyle_and_junit_reporters_emit_xml() {
+    let dir = tempdir().unwrap();
+    let broken = dir.path().join("broken.yaml");
+    fs::write(&broken, "foo: [bar\n").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--reporter", "checkstyle"])
+        .arg(&broken)
+        .assert()
+        .code(2)
+        .stdout(
+            predicate::str::contains("<checkstyle version=\"4.3\">")
+                .and(predicate::str::contains("severity=\"error\""))
+                .and(predicate::str::contains("source=\"yars.parse\"")),
+        );
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--check", "--no-cache", "--reporter", "junit"])
+        .arg(&broken)
+        .assert()
+        .code(2)
+        .stdout(
+            predicate::str::contains("tests=\"1\" failures=\"0\" errors=\"1\"")
+                .and(predicate::str::contains("<error type=\"parse\"")),
+        );
+}
+
+#[test]
+fn github_annotations_enabled_inside_actions() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "a: 1\nc: 3\nb: 2\n").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--check", "--no-cache"])
+        .arg(&file_path)
+        .assert()
+        .code(1)
+        .stdout(
+            predicate::str::contains(format!("::warning file={},line=2,", file_path.display()))
+                .and(predicate::str::contains("Checked 1 file(s); 1 would change.")),
+        );
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--check", "--no-cache", "--reporter", "text"])
+        .arg(&file_path)
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("::warning").not());
+}
+
+#[test]
+fn filter_mode_formats_stdin() {
+    assert_cmd::Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--filter", "specs/table.yaml"])
+        .write_stdin("b: 1\na: 2\n")
+        .assert()
+        .success()
+        .stdout("a: 2\nb: 1\n");
+}
+
+#[test]
+fn filter_mode_passes_invalid_yaml_through() {
+    assert_cmd::Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("--filter")
+        .write_stdin("foo: [bar\n")
+        .assert()
+        .success()
+        .stdout("foo: [bar\n")
+        .stderr(predicate::str::contains("left unchanged"));
+}
+
+#[test]
+fn watch_mode_formats_files_as_they_are_saved() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\na: 2\n").unwrap();
+
+    let mut child = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--watch", "--no-cache"])
+        .arg(dir.path())
+        .env_remove("GITHUB_ACTIONS")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = std::io::BufReader::new(child.stderr.take().unwrap());
+
+    let wait_for = |expected: &str| {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while std::time::Instant::now() < deadline {
+            if read_file(&spec) == expected {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        false
+    };
+
+    let initial = wait_for("a: 2\nb: 1\n");
+    let mut banner = String::new();
+    while !banner.starts_with("Watching") {
+        banner.clear();
+        if std::io::BufRead::read_line(&mut stderr, &mut banner).unwrap() == 0 {
+            break;
+        }
+    }
+    fs::write(&spec, "d: 1\nc: 2\n").unwrap();
+    let saved = wait_for("c: 2\nd: 1\n");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(initial, "initial pass did not format the file");
+    assert!(saved, "saved file was not reformatted");
+}
+
+#[cfg(unix)]
+#[test]
+fn use_daemon_formats_through_socket_and_falls_back() {
+    let dir = tempdir().unwrap();
+    let socket = dir.path().join("yars.sock");
+    let file = dir.path().join("spec.yaml");
+
+    // No daemon yet: formatting happens in-process.
+    fs::write(&file, "b: 1\na: 2\n").unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--no-cache", "--use-daemon", "--daemon-socket"])
+        .arg(&socket)
+        .arg(&file)
+        .assert()
+        .success();
+    assert_eq!(read_file(&file), "a: 2\nb: 1\n");
+
+    let mut daemon = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !socket.exists() && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    fs::write(&file, "d: 1\nc: 2\n").unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--no-cache", "--use-daemon", "--daemon-socket"])
+        .arg(&socket)
+        .arg(&file)
+        .assert()
+        .success();
+    assert_eq!(read_file(&file), "c: 2\nd: 1\n");
+
+    // Failures are reported with the daemon's code and message.
+    fs::write(&file, "- a\n- b\n").unwrap();
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--no-cache", "--use-daemon", "--daemon-socket"])
+        .arg(&socket)
+        .arg(&file)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Error[Y002]").and(predicate::str::contains("Top-level lists")));
+
+    yars_yaml_formatter::daemon::Client::connect(&socket)
+        .unwrap()
+        .shutdown()
+        .unwrap();
+    assert!(daemon.wait().unwrap().success());
+}
+
+#[test]
+fn backup_flag_keeps_original_contents() {
+    let dir = tempdir().unwrap();
+    let file_path = dir.path().join("sample.yaml");
+    fs::write(&file_path, "b: 1\na: 2\n").unwrap();
+
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .args(["--no-cache", "--backup"])
+        .arg(&file_path)
+        .assert()
+        .success();
+
+    assert!(read_file(&file_path).starts_with("a: 2"));
+    assert_eq!(read_file(&dir.path().join("sample.yaml.bak")), "b: 1\na: 2\n");
+}
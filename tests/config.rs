@@ -0,0 +1,180 @@
+use std::fs;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::config::{Config, ConfigResolver};
+use yars_yaml_formatter::{
+    format_yaml_string_with_options, DocumentMarkers, FormatOptions, LineEnding, YamlFormatError,
+};
+
+#[test]
+fn nearer_config_overrides_parent() {
+    let dir = tempdir().unwrap();
+    let nested = dir.path().join("specs/bronze");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nindent = 4\n").unwrap();
+    fs::write(dir.path().join("specs/.yars.toml"), "indent = 3\n").unwrap();
+
+    let mut resolver = ConfigResolver::new();
+    let top = resolver.resolve(&dir.path().join("a.yaml")).unwrap();
+    let deep = resolver.resolve(&nested.join("b.yaml")).unwrap();
+
+    assert_eq!(top.format_options().unwrap().indent, 4);
+    assert_eq!(deep.format_options().unwrap().indent, 3);
+    assert_eq!(deep.sources.len(), 2);
+    assert!(deep.sources[1].ends_with("specs/.yars.toml"));
+}
+
+#[test]
+fn pyproject_tool_table_is_discovered() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("pyproject.toml"),
+        "[project]\nname = \"tablespec\"\n\n[tool.yars]\nroot = true\nindent = 4\n",
+    )
+    .unwrap();
+
+    let resolved = ConfigResolver::new()
+        .resolve(&dir.path().join("table.yaml"))
+        .unwrap();
+    assert_eq!(resolved.format_options().unwrap().indent, 4);
+}
+
+#[test]
+fn pyproject_without_tool_table_is_ignored() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("pyproject.toml");
+    fs::write(&path, "[project]\nname = \"tablespec\"\n").unwrap();
+
+    assert_eq!(Config::from_file(&path).unwrap(), None);
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join(".yars.toml");
+    fs::write(&path, "indnet = 4\n").unwrap();
+
+    let err = Config::from_file(&path).unwrap_err();
+    assert!(matches!(err, YamlFormatError::InvalidConfig(_, _)));
+}
+
+#[test]
+fn indent_option_controls_nesting() {
+    let options = FormatOptions {
+        indent: 4,
+        ..FormatOptions::default()
+    };
+    let formatted =
+        format_yaml_string_with_options("b:\n  c: 1\n  d: [x]\na: 1\n", &options).unwrap();
+    assert_eq!(formatted, "a: 1\nb:\n    c: 1\n    d:\n        - x\n");
+}
+
+#[test]
+fn editorconfig_sections_map_onto_options() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join(".editorconfig"),
+        "root = true\n\n[*]\nindent_size = 4\nend_of_line = crlf\n\n[*.{yml,yaml}]\ninsert_final_newline = false\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\n").unwrap();
+
+    let options = ConfigResolver::new()
+        .resolve(&dir.path().join("specs/table.yaml"))
+        .unwrap()
+        .format_options()
+        .unwrap();
+    assert_eq!(options.indent, 4);
+    assert_eq!(options.line_ending, LineEnding::Crlf);
+    assert!(!options.final_newline);
+}
+
+#[test]
+fn yars_config_takes_precedence_over_editorconfig() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".editorconfig"), "root = true\n[*]\nindent_size = 4\n").unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nindent = 2\n").unwrap();
+
+    let resolved = ConfigResolver::new()
+        .resolve(&dir.path().join("table.yaml"))
+        .unwrap();
+    assert_eq!(resolved.format_options().unwrap().indent, 2);
+    assert_eq!(resolved.sources.len(), 2);
+}
+
+#[test]
+fn line_ending_and_final_newline_options() {
+    let options = FormatOptions {
+        line_ending: LineEnding::Crlf,
+        final_newline: false,
+        ..FormatOptions::default()
+    };
+    let formatted =
+        format_yaml_string_with_options("b: \"x\\ny\"\na: 1\n", &options).unwrap();
+    assert_eq!(formatted, "a: 1\r\nb: |-\r\n  x\r\n  y");
+}
+
+#[test]
+fn auto_line_ending_preserves_the_dominant_terminator() {
+    let options = FormatOptions {
+        line_ending: LineEnding::Auto,
+        ..FormatOptions::default()
+    };
+    let crlf = "b: |-\r\n  x\r\n  y\r\na: 1\r\n";
+    assert_eq!(LineEnding::detect(crlf), LineEnding::Crlf);
+    assert_eq!(
+        format_yaml_string_with_options(crlf, &options).unwrap(),
+        "a: 1\r\nb: |-\r\n  x\r\n  y\r\n"
+    );
+    assert_eq!(
+        format_yaml_string_with_options("b: 1\na: 2\r\nc: 3\n", &options).unwrap(),
+        "a: 2\nb: 1\nc: 3\n"
+    );
+
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nline_ending = \"auto\"\n").unwrap();
+    let options = ConfigResolver::new()
+        .resolve(&dir.path().join("a.yaml"))
+        .unwrap()
+        .format_options()
+        .unwrap();
+    assert_eq!(options.line_ending, LineEnding::Auto);
+}
+
+#[test]
+fn carriage_returns_in_values_stay_quoted() {
+    let options = FormatOptions {
+        line_ending: LineEnding::Crlf,
+        ..FormatOptions::default()
+    };
+    let formatted = format_yaml_string_with_options("a: \"x\\r\\ny\"\n", &options).unwrap();
+    assert_eq!(formatted, "a: \"x\\r\\ny\"\r\n");
+}
+
+#[test]
+fn document_markers_option() {
+    let input = "# spec\n%YAML 1.2\n---\nb: 1\na: 2\n...\n";
+    let with = |document_markers| FormatOptions {
+        document_markers,
+        ..FormatOptions::default()
+    };
+
+    let never = format_yaml_string_with_options(input, &with(DocumentMarkers::Never)).unwrap();
+    assert_eq!(never, "a: 2\nb: 1\n");
+    let preserve = format_yaml_string_with_options(input, &with(DocumentMarkers::Preserve)).unwrap();
+    assert_eq!(preserve, "%YAML 1.2\n---\na: 2\nb: 1\n...\n");
+    let bare = format_yaml_string_with_options("b: 1\n", &with(DocumentMarkers::Preserve)).unwrap();
+    assert_eq!(bare, "b: 1\n");
+    let always = format_yaml_string_with_options("b: 1\n", &with(DocumentMarkers::Always)).unwrap();
+    assert_eq!(always, "---\nb: 1\n...\n");
+
+    let crlf = FormatOptions {
+        line_ending: LineEnding::Crlf,
+        final_newline: false,
+        ..with(DocumentMarkers::Preserve)
+    };
+    assert_eq!(
+        format_yaml_string_with_options("---\na: 1\n...\n", &crlf).unwrap(),
+        "---\r\na: 1\r\n..."
+    );
+}
@@ -0,0 +1,98 @@
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::tempdir;
+use yars_yaml_formatter::daemon::{serve, Client};
+use yars_yaml_formatter::{ErrorCode, FormatOptions};
+
+fn connect(socket: &Path) -> Client {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match Client::connect(socket) {
+            Ok(client) => return client,
+            Err(err) if Instant::now() > deadline => panic!("daemon did not start: {err}"),
+            Err(_) => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+#[test]
+fn daemon_formats_checks_and_shuts_down() {
+    let dir = tempdir().unwrap();
+    let socket = dir.path().join("yars.sock");
+    let server = {
+        let socket = socket.clone();
+        thread::spawn(move || serve(&socket))
+    };
+
+    let mut client = connect(&socket);
+    let options = FormatOptions::default();
+    assert_eq!(
+        client.format("b: 1\na: 2\n", &options).unwrap().unwrap(),
+        "a: 2\nb: 1\n"
+    );
+
+    let error = client.format("- a\n- b\n", &options).unwrap().unwrap_err();
+    assert_eq!(error.kind(), "top_level_list");
+    assert_eq!(error.code(), ErrorCode::UnsupportedRoot);
+    assert_eq!(error.location(), Some((1, 1)));
+    assert!(error.to_string().starts_with("Top-level lists are not supported"));
+
+    let mut raw = UnixStream::connect(&socket).unwrap();
+    writeln!(raw, r#"{{"jsonrpc": "2.0", "id": 1, "method": "check", "params": {{"text": "b: 1\na: 2\n"}}}}"#).unwrap();
+    let mut reply = String::new();
+    BufReader::new(&raw).read_line(&mut reply).unwrap();
+    let reply: serde_json::Value = serde_json::from_str(&reply).unwrap();
+    assert_eq!(reply["result"], serde_json::json!({"changed": true}));
+    // Shutdown waits for open connections.
+    drop(raw);
+
+    client.shutdown().unwrap();
+    server.join().unwrap().unwrap();
+    assert!(!socket.exists());
+}
+
+#[test]
+fn second_daemon_on_live_socket_is_refused() {
+    let dir = tempdir().unwrap();
+    let socket = dir.path().join("yars.sock");
+    let server = {
+        let socket = socket.clone();
+        thread::spawn(move || serve(&socket))
+    };
+    let mut client = connect(&socket);
+
+    let err = serve(&socket).unwrap_err();
+    assert_eq!(err.kind(), "daemon");
+
+    client.shutdown().unwrap();
+    server.join().unwrap().unwrap();
+}
+
+#[test]
+fn default_socket_without_runtime_dir_lives_in_a_private_directory() {
+    let dir = tempdir().unwrap();
+    let mut daemon = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("daemon")
+        .env_remove("XDG_RUNTIME_DIR")
+        .env("TMPDIR", dir.path())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let uid = std::fs::metadata(dir.path()).unwrap().uid();
+    let private = dir.path().join(format!("yars-format-{uid}"));
+    let mut client = connect(&private.join("daemon.sock"));
+    let mode = std::fs::metadata(&private).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+
+    client.shutdown().unwrap();
+    assert!(daemon.wait().unwrap().success());
+}
@@ -0,0 +1,76 @@
+use yars_yaml_formatter::diff::{change_stats, text_edits, Position, PositionEncoding, TextEdit};
+use yars_yaml_formatter::{format_yaml_string, format_yaml_text_edits, FormatOptions};
+
+/// Apply edits by converting positions back to byte offsets.
+fn apply(text: &str, edits: &[TextEdit], encoding: PositionEncoding) -> String {
+    let offset = |position: Position| {
+        let line_start: usize = text
+            .split_inclusive('\n')
+            .take(position.line)
+            .map(str::len)
+            .sum();
+        let line = &text[line_start..];
+        let mut units = 0;
+        let within = line
+            .char_indices()
+            .find(|(_, ch)| {
+                let done = units >= position.column;
+                units += match encoding {
+                    PositionEncoding::Utf8 => ch.len_utf8(),
+                    PositionEncoding::Utf16 => ch.len_utf16(),
+                };
+                done
+            })
+            .map_or(line.len(), |(idx, _)| idx);
+        line_start + within
+    };
+
+    let mut result = text.to_owned();
+    for edit in edits.iter().rev() {
+        let span = offset(edit.range.start)..offset(edit.range.end);
+        result.replace_range(span, &edit.new_text);
+    }
+    result
+}
+
+#[test]
+fn edits_reproduce_formatted_output() {
+    let input = "z: 1\nb:\n  c: 1\n  d: [x]\na: \"é\"\n";
+    let formatted = format_yaml_string(input).unwrap();
+    for encoding in [PositionEncoding::Utf8, PositionEncoding::Utf16] {
+        let edits = format_yaml_text_edits(input, &FormatOptions::default(), encoding).unwrap();
+        assert_eq!(apply(input, &edits, encoding), formatted);
+    }
+}
+
+#[test]
+fn formatted_input_needs_no_edits() {
+    let edits =
+        format_yaml_text_edits("a: 1\n", &FormatOptions::default(), PositionEncoding::Utf16)
+            .unwrap();
+    assert!(edits.is_empty());
+}
+
+#[test]
+fn unterminated_last_line_columns_follow_encoding() {
+    let original = "a: 1\nb: 😀";
+    let formatted = "a: 1\nb: 😀\n";
+
+    let utf8 = text_edits(original, formatted, PositionEncoding::Utf8);
+    let utf16 = text_edits(original, formatted, PositionEncoding::Utf16);
+    assert_eq!(utf8.len(), 1);
+    assert_eq!(utf8[0].range.start, Position { line: 1, column: 0 });
+    assert_eq!(utf8[0].range.end, Position { line: 1, column: 7 });
+    assert_eq!(utf16[0].range.end, Position { line: 1, column: 5 });
+    assert_eq!(utf16[0].new_text, "b: 😀\n");
+}
+
+#[test]
+fn stats_count_hunks_not_shifted_lines() {
+    let original = "a: 1\nb: 2\nc: 3\nd: 4\n";
+    let formatted = "a: 1\nx: 0\nb: 2\nc: 3\nd: 4\n";
+
+    let stats = change_stats(original, formatted);
+    assert_eq!(stats.lines_changed, 1);
+    assert_eq!(stats.first_changed_line, Some(2));
+}
@@ -0,0 +1,109 @@
+use std::fs;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::config::ConfigResolver;
+use yars_yaml_formatter::duplicates::DuplicateKey;
+use yars_yaml_formatter::{
+    format_yaml_range_with_options, format_yaml_string, format_yaml_string_with_options, DuplicateKeys,
+    ErrorCode, FormatOptions, YamlFormatError,
+};
+
+fn with(duplicate_keys: DuplicateKeys) -> FormatOptions {
+    FormatOptions {
+        duplicate_keys,
+        ..FormatOptions::default()
+    }
+}
+
+#[test]
+fn every_duplicate_is_reported_with_both_locations() {
+    let input = "\
+name: first
+jobs:
+  build:
+    - step: a
+      step: b
+  test: {}
+  build: []
+name: second
+name: third
+";
+    let err = format_yaml_string(input).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::DuplicateKey);
+    assert_eq!(err.kind(), "duplicate_keys");
+    assert_eq!(err.location(), Some((5, 7)));
+
+    let YamlFormatError::DuplicateKeys { duplicates, .. } = &err else {
+        panic!("unexpected error: {err:?}");
+    };
+    let key = |key: &str, first, duplicate| DuplicateKey {
+        key: key.to_owned(),
+        first: Some(first),
+        duplicate: Some(duplicate),
+    };
+    assert_eq!(
+        duplicates,
+        &[
+            key("jobs.build[0].step", (4, 7), (5, 7)),
+            key("jobs.build", (3, 3), (7, 3)),
+            key("name", (1, 1), (8, 1)),
+            key("name", (1, 1), (9, 1)),
+        ]
+    );
+    assert!(err
+        .to_string()
+        .contains("`jobs.build[0].step` at line 5, column 7 (first defined at line 4, column 7)"));
+}
+
+#[test]
+fn keys_equal_as_values_are_duplicates() {
+    let err = format_yaml_string("a: 1\n\"a\": 2\n").unwrap_err();
+    assert_eq!(err.location(), Some((2, 1)));
+
+    // Flow mappings are not scanned, so the duplicate has no position.
+    let err = format_yaml_string("outer: {a: 1, a: 2}\n").unwrap_err();
+    let YamlFormatError::DuplicateKeys { duplicates, .. } = err else {
+        panic!("unexpected error: {err:?}");
+    };
+    assert_eq!(duplicates[0].key, "outer.a");
+    assert_eq!(duplicates[0].duplicate, None);
+}
+
+#[test]
+fn duplicates_can_keep_the_first_or_last_value() {
+    let input = "b: 1\na:\n  x: 1\n  x: 2\nb: 3\n";
+    assert_eq!(
+        format_yaml_string_with_options(input, &with(DuplicateKeys::First)).unwrap(),
+        "a:\n  x: 1\nb: 1\n"
+    );
+    assert_eq!(
+        format_yaml_string_with_options(input, &with(DuplicateKeys::Last)).unwrap(),
+        "a:\n  x: 2\nb: 3\n"
+    );
+
+    assert_eq!(
+        format_yaml_range_with_options(input, 5..7, &with(DuplicateKeys::Last)).unwrap(),
+        "b: 1\na:\n  x: 2\nb: 3\n"
+    );
+}
+
+#[test]
+fn keys_with_the_same_text_sort_by_type() {
+    let numbers_first = "1: number\n\"1\": string\nnull: x\n\"null\": y\n";
+    let strings_first = "\"null\": y\n\"1\": string\nnull: x\n1: number\n";
+    assert_eq!(format_yaml_string(numbers_first).unwrap(), numbers_first);
+    assert_eq!(format_yaml_string(strings_first).unwrap(), numbers_first);
+}
+
+#[test]
+fn duplicate_keys_option_is_configurable() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nduplicate_keys = \"last\"\n").unwrap();
+
+    let options = ConfigResolver::new()
+        .resolve(&dir.path().join("a.yaml"))
+        .unwrap()
+        .format_options()
+        .unwrap();
+    assert_eq!(options.duplicate_keys, DuplicateKeys::Last);
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::config::ConfigResolver;
+use yars_yaml_formatter::encoding::{decode, encode, Encoding};
+use yars_yaml_formatter::{format_yaml_file_with_options, FormatOptions, YamlFormatError};
+
+#[test]
+fn encodings_are_detected_with_and_without_byte_order_marks() {
+    let cases: [(&[u8], Encoding, usize); 9] = [
+        (b"a: 1", Encoding::Utf8, 0),
+        (b"\xEF\xBB\xBFa: 1", Encoding::Utf8Bom, 3),
+        (b"\xFF\xFEa\0", Encoding::Utf16Le, 2),
+        (b"a\0:\0", Encoding::Utf16Le, 0),
+        (b"\xFE\xFF\0a", Encoding::Utf16Be, 2),
+        (b"\0a\0:", Encoding::Utf16Be, 0),
+        (b"\xFF\xFE\0\0a\0\0\0", Encoding::Utf32Le, 4),
+        (b"a\0\0\0", Encoding::Utf32Le, 0),
+        (b"\0\0\0a", Encoding::Utf32Be, 0),
+    ];
+    for (bytes, encoding, bom) in cases {
+        assert_eq!(Encoding::detect(bytes), (encoding, bom), "{bytes:?}");
+    }
+}
+
+#[test]
+fn every_encoding_round_trips() {
+    let text = "name: caf\u{e9} \u{1f600}\n";
+    for encoding in [
+        Encoding::Utf8,
+        Encoding::Utf8Bom,
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+    ] {
+        let bytes = encode(text, encoding);
+        let decoded = decode(&bytes, Path::new("a.yaml")).unwrap();
+        assert_eq!((decoded.text.as_str(), decoded.encoding), (text, encoding));
+        assert_eq!(decoded.encode(text, None), bytes);
+    }
+}
+
+#[test]
+fn files_are_written_back_in_their_own_encoding() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("spec.yaml");
+    fs::write(&file, encode("b: 1\na: 2\n", Encoding::Utf16Le)).unwrap();
+
+    assert!(format_yaml_file_with_options(&file, false, &FormatOptions::default()).unwrap());
+    assert_eq!(fs::read(&file).unwrap(), encode("a: 2\nb: 1\n", Encoding::Utf16Le));
+
+    // A UTF-8 byte order mark is kept, not fed to the parser.
+    fs::write(&file, encode("a: 1\n", Encoding::Utf8Bom)).unwrap();
+    assert!(!format_yaml_file_with_options(&file, false, &FormatOptions::default()).unwrap());
+}
+
+#[test]
+fn byte_order_marks_are_kept_only_where_present() {
+    let dir = tempdir().unwrap();
+    let file = dir.path().join("spec.yaml");
+    fs::write(&file, &encode("b: 1\na: 2\n", Encoding::Utf16Be)[2..]).unwrap();
+
+    assert!(format_yaml_file_with_options(&file, false, &FormatOptions::default()).unwrap());
+    assert_eq!(fs::read(&file).unwrap(), &encode("a: 2\nb: 1\n", Encoding::Utf16Be)[2..]);
+}
+
+#[test]
+fn configured_encoding_converts_files() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".editorconfig"), "root = true\n\n[*.yaml]\ncharset = utf-8\n").unwrap();
+    let file = dir.path().join("spec.yaml");
+    fs::write(&file, encode("a: 1\n", Encoding::Utf16Be)).unwrap();
+
+    let options = ConfigResolver::new().resolve(&file).unwrap().format_options().unwrap();
+    assert_eq!(options.encoding, Some(Encoding::Utf8));
+    assert!(format_yaml_file_with_options(&file, false, &options).unwrap());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "a: 1\n");
+}
+
+#[test]
+fn invalid_sequences_report_their_byte_offset() {
+    let err = decode(b"a: 1\nb: \xC3(\n", Path::new("bad.yaml")).unwrap_err();
+    assert!(matches!(
+        err,
+        YamlFormatError::InvalidEncoding { encoding: Encoding::Utf8, offset: 8, .. }
+    ));
+    assert_eq!(err.to_string(), "Invalid utf-8 in bad.yaml at byte 8");
+
+    // An unpaired surrogate after the byte order mark and one character.
+    let err = decode(b"\xFF\xFEa\0\x00\xD8", Path::new("bad.yaml")).unwrap_err();
+    assert!(matches!(err, YamlFormatError::InvalidEncoding { offset: 4, .. }));
+}
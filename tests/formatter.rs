@@ -5,8 +5,10 @@ use std::path::PathBuf;
 use serde_yaml::{self, Value};
 use tempfile::NamedTempFile;
 use yars_yaml_formatter::{
-    format_yaml_dict, format_yaml_file, format_yaml_files, format_yaml_string, YamlFormatError,
+    format_yaml_dict, format_yaml_file, format_yaml_files, format_yaml_string, ErrorCategory,
+    ErrorCode, YamlFormatError,
 };
+use yars_yaml_formatter::report::FileStatus;
 
 #[path = "support/mod.rs"]
 mod support;
@@ -108,16 +110,72 @@ fn escape_sequences_round_trip() {
 fn top_level_list_rejected() {
     let yaml = "- a\n- b\n";
     let err = format_yaml_string(yaml).unwrap_err();
-    matches!(
-        err,
-        YamlFormatError::TopLevelList
-    );
+    assert!(matches!(err, YamlFormatError::TopLevelList { .. }));
+}
+
+#[test]
+fn top_level_list_reports_where_the_list_starts() {
+    let yaml = "# generated\n%YAML 1.2\n---\n\n  - a\n  - b\n";
+    let err = format_yaml_string(yaml).unwrap_err();
+    assert_eq!(err.location(), Some((5, 3)));
+
+    let err = format_yaml_string("--- [a, b]\n").unwrap_err();
+    assert_eq!(err.location(), Some((1, 5)));
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "- a").unwrap();
+    match format_yaml_file(file.path(), true).unwrap_err() {
+        YamlFormatError::TopLevelList { path, line, column } => {
+            assert_eq!(path.as_deref(), Some(file.path().display().to_string().as_str()));
+            assert_eq!((line, column), (1, 1));
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
 }
 
 #[test]
 fn invalid_yaml_raises_error() {
     let err = format_yaml_string("foo: [bar").unwrap_err();
-    assert!(matches!(err, YamlFormatError::Format(_)));
+    assert!(matches!(err, YamlFormatError::Parse { .. }));
+}
+
+#[test]
+fn parse_errors_carry_location_and_source() {
+    let err = format_yaml_string("---\na: 1\nb: [c\n").unwrap_err();
+    let (line, column) = err.location().expect("parse error has a location");
+    assert_eq!(line, 4);
+    assert_eq!(column, 1);
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn parse_errors_name_the_file() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "a: 1\n  b: 2\n").unwrap();
+    let err = format_yaml_file(file.path(), true).unwrap_err();
+    match err {
+        YamlFormatError::Parse { path, line, .. } => {
+            assert_eq!(path.as_deref(), Some(file.path().display().to_string().as_str()));
+            assert_eq!(line, 2);
+        }
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[test]
+fn errors_carry_stable_codes() {
+    let cases = [
+        ("a: [1\n", ErrorCode::Parse, ErrorCategory::Syntax),
+        ("- a\n", ErrorCode::UnsupportedRoot, ErrorCategory::Structure),
+        ("a: 1\na: 2\n", ErrorCode::DuplicateKey, ErrorCategory::Structure),
+    ];
+    for (yaml, code, category) in cases {
+        let err = format_yaml_string(yaml).unwrap_err();
+        assert_eq!(err.code(), code, "{yaml:?}");
+        assert_eq!(code.category(), category);
+    }
+    assert_eq!(ErrorCode::Parse.to_string(), "Y001");
+    assert_eq!(serde_json::to_value(ErrorCode::Io).unwrap(), "Y010");
 }
 
 #[test]
@@ -132,7 +190,7 @@ fn format_yaml_dict_requires_mapping() {
     let err = format_yaml_dict(&vec!["a", "b"]).unwrap_err();
     match err {
         YamlFormatError::Format(message) => assert!(message.contains("Expected dict")),
-        YamlFormatError::TopLevelList => {}
+        YamlFormatError::TopLevelList { .. } => {}
         other => panic!("unexpected error: {other:?}"),
     };
 }
@@ -164,11 +222,19 @@ fn batch_formatting_aggregates_errors() {
 
     let missing_path = PathBuf::from("surely-does-not-exist.yaml");
 
-    let (changed, errors, messages) =
-        format_yaml_files([good_path.as_path(), missing_path.as_path()], false);
-
-    assert_eq!(changed, 1);
-    assert_eq!(errors, 1);
-    assert_eq!(messages.len(), 1);
-    assert!(messages[0].contains("File not found"));
+    let report = format_yaml_files([good_path.as_path(), missing_path.as_path()], false);
+
+    assert_eq!(report.changed(), 1);
+    assert_eq!(report.errors(), 1);
+    assert_eq!(report.files.len(), 2);
+    assert_eq!(report.files[0].path, good_path);
+    assert_eq!(report.files[0].status, FileStatus::Changed);
+    assert_eq!(report.files[0].stats.lines_changed, 2);
+    assert!(report.files[0].error.is_none());
+    assert_eq!(report.files[1].path, missing_path);
+    assert_eq!(report.files[1].status, FileStatus::Error);
+    let error = report.files[1].error.as_ref().unwrap();
+    assert!(error.to_string().contains("File not found"));
+    assert_eq!(error.code(), ErrorCode::Io);
+    assert_eq!(fs::read_to_string(&good_path).unwrap(), "a: 1\nb: 2\n");
 }
@@ -0,0 +1,177 @@
+use assert_cmd::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::{tempdir, TempDir};
+use yars_yaml_formatter::encoding::{encode, Encoding};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=yars", "-c", "user.email=yars@example.com"])
+        .args(args)
+        .output()
+        .expect("git should run");
+    assert!(output.status.success(), "git {args:?} failed: {output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn repo() -> TempDir {
+    let dir = tempdir().unwrap();
+    git(dir.path(), &["init", "-q"]);
+    fs::write(dir.path().join(".yars.toml"), "root = true\n").unwrap();
+    dir
+}
+
+fn yars(dir: &Path) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"));
+    cmd.current_dir(dir).arg("--no-cache").env_remove("GITHUB_ACTIONS");
+    cmd
+}
+
+#[test]
+fn staged_formats_and_restages_index_content() {
+    let dir = repo();
+    fs::write(dir.path().join("spec.yaml"), "b: 1\na: 2\n").unwrap();
+    fs::write(dir.path().join("notes.txt"), "b: 1\n").unwrap();
+    git(dir.path(), &["add", "spec.yaml", "notes.txt"]);
+
+    yars(dir.path())
+        .arg("--staged")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Formatted 1 file(s); 1 updated"));
+
+    assert_eq!(git(dir.path(), &["show", ":spec.yaml"]), "a: 2\nb: 1\n");
+    assert_eq!(fs::read_to_string(dir.path().join("spec.yaml")).unwrap(), "a: 2\nb: 1\n");
+    assert_eq!(git(dir.path(), &["diff", "--name-only"]), "");
+}
+
+#[test]
+fn staged_leaves_unstaged_edits_in_working_tree() {
+    let dir = repo();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\na: 2\n").unwrap();
+    git(dir.path(), &["add", "spec.yaml"]);
+    fs::write(&spec, "b: 1\na: 2\nc: 3\n").unwrap();
+
+    yars(dir.path()).arg("--staged").assert().success();
+
+    assert_eq!(git(dir.path(), &["show", ":spec.yaml"]), "a: 2\nb: 1\n");
+    assert_eq!(fs::read_to_string(&spec).unwrap(), "b: 1\na: 2\nc: 3\n");
+}
+
+#[test]
+fn staged_compares_working_tree_after_line_ending_conversion() {
+    let dir = repo();
+    git(dir.path(), &["config", "core.autocrlf", "true"]);
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\r\na: 2\r\n").unwrap();
+    git(dir.path(), &["add", "spec.yaml"]);
+
+    yars(dir.path()).arg("--staged").assert().success();
+
+    assert_eq!(git(dir.path(), &["show", ":spec.yaml"]), "a: 2\nb: 1\n");
+    assert_eq!(git(dir.path(), &["diff", "--name-only"]), "");
+}
+
+#[test]
+fn staged_content_keeps_its_encoding() {
+    let dir = repo();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, encode("b: 1\na: 2\n", Encoding::Utf16Le)).unwrap();
+    git(dir.path(), &["add", "spec.yaml"]);
+
+    yars(dir.path()).arg("--staged").assert().success();
+
+    let formatted = encode("a: 2\nb: 1\n", Encoding::Utf16Le);
+    assert_eq!(fs::read(&spec).unwrap(), formatted);
+    assert_eq!(git(dir.path(), &["diff", "--name-only"]), "");
+}
+
+#[test]
+fn staged_check_inspects_index_not_working_tree() {
+    let dir = repo();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\na: 2\n").unwrap();
+    git(dir.path(), &["add", "spec.yaml"]);
+    fs::write(&spec, "a: 2\nb: 1\n").unwrap();
+
+    yars(dir.path()).args(["--staged", "--check"]).assert().code(1);
+    assert_eq!(git(dir.path(), &["show", ":spec.yaml"]), "b: 1\na: 2\n");
+}
+
+#[test]
+fn since_selects_only_changed_yaml() {
+    let dir = repo();
+    fs::write(dir.path().join("old.yaml"), "b: 1\na: 2\n").unwrap();
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-qm", "initial"]);
+
+    fs::write(dir.path().join("new.yaml"), "d: 1\nc: 2\n").unwrap();
+    git(dir.path(), &["add", "new.yaml"]);
+
+    yars(dir.path())
+        .args(["--since", "HEAD", "--check"])
+        .assert()
+        .code(1)
+        .stdout(predicates::str::contains("Checked 1 file(s); 1 would change."));
+}
+
+fn merge_driver(dir: &Path) -> Command {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"));
+    cmd.current_dir(dir).arg("merge-driver");
+    cmd
+}
+
+#[test]
+fn merge_driver_writes_structural_merge_into_ours() {
+    let dir = repo();
+    let base = dir.path().join("base.yaml");
+    let ours = dir.path().join("ours.yaml");
+    let theirs = dir.path().join("theirs.yaml");
+    fs::write(&base, "a: 1\nb: 2\n").unwrap();
+    fs::write(&ours, "a: 10\nb: 2\n").unwrap();
+    fs::write(&theirs, "a: 1\nb: 20\n").unwrap();
+
+    merge_driver(dir.path())
+        .args([ "base.yaml", "ours.yaml", "theirs.yaml"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&ours).unwrap(), "a: 10\nb: 20\n");
+}
+
+#[test]
+fn merge_driver_keeps_the_encoding_of_ours() {
+    let dir = repo();
+    let ours = dir.path().join("ours.yaml");
+    fs::write(dir.path().join("base.yaml"), encode("a: 1\nb: 2\n", Encoding::Utf16Le)).unwrap();
+    fs::write(&ours, encode("a: 10\nb: 2\n", Encoding::Utf16Le)).unwrap();
+    fs::write(dir.path().join("theirs.yaml"), "a: 1\nb: 20\n").unwrap();
+
+    merge_driver(dir.path())
+        .args(["base.yaml", "ours.yaml", "theirs.yaml"])
+        .assert()
+        .success();
+    assert_eq!(fs::read(&ours).unwrap(), encode("a: 10\nb: 20\n", Encoding::Utf16Le));
+}
+
+#[test]
+fn merge_driver_conflict_leaves_markers() {
+    let dir = repo();
+    let base = dir.path().join("base.yaml");
+    let ours = dir.path().join("ours.yaml");
+    let theirs = dir.path().join("theirs.yaml");
+    fs::write(&base, "a: 1\n").unwrap();
+    fs::write(&ours, "a: 2\n").unwrap();
+    fs::write(&theirs, "a: 3\n").unwrap();
+
+    merge_driver(dir.path())
+        .args([ "base.yaml", "ours.yaml", "theirs.yaml", "table.yaml"])
+        .assert()
+        .code(1)
+        .stderr(predicates::str::contains("table.yaml: conflicting changes to a"));
+    let merged = fs::read_to_string(&ours).unwrap();
+    assert!(merged.contains("<<<<<<< ours") && merged.contains(">>>>>>> theirs"));
+}
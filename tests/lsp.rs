@@ -0,0 +1,168 @@
+use assert_cmd::Command;
+use serde_json::{json, Value};
+use std::fs;
+use tempfile::tempdir;
+
+fn frame(messages: &[Value]) -> Vec<u8> {
+    let mut input = Vec::new();
+    for message in messages {
+        let body = message.to_string();
+        input.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes());
+    }
+    input
+}
+
+fn unframe(mut output: &[u8]) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while !output.is_empty() {
+        let text = std::str::from_utf8(output).unwrap();
+        let header_end = text.find("\r\n\r\n").expect("header terminator");
+        let length: usize = text[..header_end]
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let start = header_end + 4;
+        messages.push(serde_json::from_slice(&output[start..start + length]).unwrap());
+        output = &output[start + length..];
+    }
+    messages
+}
+
+/// Run a scripted session: initialize, the given messages, shutdown and exit.
+fn session(script: &[Value]) -> Vec<Value> {
+    let mut messages = vec![
+        json!({"jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {"capabilities": {}}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+    ];
+    messages.extend_from_slice(script);
+    messages.push(json!({"jsonrpc": "2.0", "id": 99, "method": "shutdown"}));
+    messages.push(json!({"jsonrpc": "2.0", "method": "exit"}));
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("lsp")
+        .write_stdin(frame(&messages))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    unframe(&output)
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    messages
+        .iter()
+        .find(|message| message["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {id}"))
+}
+
+/// Apply line-granular LSP edits (as the server produces) to `text`.
+fn apply(text: &str, edits: &[Value]) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut result = String::new();
+    let mut next = 0;
+    for edit in edits {
+        let start = edit["range"]["start"]["line"].as_u64().unwrap() as usize;
+        let end = edit["range"]["end"]["line"].as_u64().unwrap() as usize;
+        result.push_str(&lines[next..start].concat());
+        result.push_str(edit["newText"].as_str().unwrap());
+        next = end;
+    }
+    result.push_str(&lines[next..].concat());
+    result
+}
+
+fn did_open(uri: &str, text: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": {"textDocument": {"uri": uri, "languageId": "yaml", "version": 1, "text": text}},
+    })
+}
+
+#[test]
+fn formatting_replaces_whole_document() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".yars.toml"), "root = true\nindent = 4\n").unwrap();
+    let uri = format!("file://{}/table.yaml", dir.path().display());
+
+    let original = "b:\n  c: 1\na: 1\n";
+    let messages = session(&[
+        did_open(&uri, original),
+        json!({
+            "jsonrpc": "2.0", "id": 1, "method": "textDocument/formatting",
+            "params": {"textDocument": {"uri": uri}, "options": {"tabSize": 2, "insertSpaces": true}},
+        }),
+    ]);
+
+    let init = response(&messages, 0);
+    assert_eq!(init["result"]["capabilities"]["documentFormattingProvider"], true);
+    assert_eq!(init["result"]["capabilities"]["positionEncoding"], "utf-16");
+    let edits = response(&messages, 1)["result"].as_array().unwrap();
+    assert!(!edits.is_empty());
+    assert_eq!(apply(original, edits), "a: 1\nb:\n    c: 1\n");
+    assert_eq!(response(&messages, 99)["result"], Value::Null);
+}
+
+#[test]
+fn formatted_document_needs_no_edits() {
+    let uri = "untitled:Untitled-1";
+    let messages = session(&[
+        did_open(uri, "a: 1\n"),
+        json!({
+            "jsonrpc": "2.0", "id": 1, "method": "textDocument/rangeFormatting",
+            "params": {
+                "textDocument": {"uri": uri},
+                "range": {"start": {"line": 0, "character": 0}, "end": {"line": 1, "character": 0}},
+                "options": {"tabSize": 2, "insertSpaces": true},
+            },
+        }),
+    ]);
+    assert_eq!(response(&messages, 1)["result"], json!([]));
+}
+
+#[test]
+fn parse_errors_are_published_as_diagnostics() {
+    let uri = "untitled:broken.yaml";
+    let messages = session(&[did_open(uri, "a: 1\nb: [unclosed\n")]);
+
+    let published = messages
+        .iter()
+        .find(|message| message["method"] == "textDocument/publishDiagnostics")
+        .expect("diagnostics notification");
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["code"], "Y001");
+    assert!(diagnostics[0]["range"]["start"]["line"].as_u64().unwrap() >= 1);
+}
+
+#[test]
+fn exit_without_shutdown_fails() {
+    let input = frame(&[json!({"jsonrpc": "2.0", "method": "exit"})]);
+    Command::new(assert_cmd::cargo::cargo_bin!("yars_format"))
+        .arg("lsp")
+        .write_stdin(input)
+        .assert()
+        .code(1);
+}
+
+#[test]
+fn range_formatting_leaves_the_rest_of_the_document() {
+    let uri = "untitled:partial.yaml";
+    let original = "b: [x]\na: 1\n";
+    let messages = session(&[
+        did_open(uri, original),
+        json!({
+            "jsonrpc": "2.0", "id": 1, "method": "textDocument/rangeFormatting",
+            "params": {
+                "textDocument": {"uri": uri},
+                "range": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 6}},
+                "options": {"tabSize": 2, "insertSpaces": true},
+            },
+        }),
+    ]);
+    let edits = response(&messages, 1)["result"].as_array().unwrap();
+    assert_eq!(apply(original, edits), "b:\n  - x\na: 1\n");
+}
@@ -0,0 +1,51 @@
+use yars_yaml_formatter::merge::{merge_yaml_strings, MergeConflict, MergeOutcome};
+use yars_yaml_formatter::FormatOptions;
+
+fn merge(base: &str, ours: &str, theirs: &str) -> MergeOutcome {
+    merge_yaml_strings(base, ours, theirs, &FormatOptions::default()).unwrap()
+}
+
+#[test]
+fn independent_key_changes_merge() {
+    let base = "a: 1\nb: 2\nc: 3\n";
+    let ours = "a: 10\nb: 2\nc: 3\n";
+    let theirs = "a: 1\nb: 2\nc: 3\nd: 4\n";
+    assert_eq!(
+        merge(base, ours, theirs),
+        MergeOutcome::Merged("a: 10\nb: 2\nc: 3\nd: 4\n".to_owned())
+    );
+}
+
+#[test]
+fn same_key_changed_differently_conflicts() {
+    let base = "table:\n  name: a\n";
+    let ours = "table:\n  name: b\n";
+    let theirs = "table:\n  name: c\n";
+    assert_eq!(
+        merge(base, ours, theirs),
+        MergeOutcome::Conflicted(vec![MergeConflict {
+            path: "table.name".to_owned()
+        }])
+    );
+}
+
+#[test]
+fn sequence_items_match_by_identity_key() {
+    let base = "columns:\n- name: id\n  type: int\n- name: dt\n  type: date\n";
+    let ours = "columns:\n- name: id\n  type: bigint\n- name: dt\n  type: date\n";
+    let theirs =
+        "columns:\n- name: id\n  type: int\n- name: dt\n  type: date\n- name: amt\n  type: decimal\n";
+    assert_eq!(
+        merge(base, ours, theirs),
+        MergeOutcome::Merged(
+            "columns:\n  - name: id\n    type: bigint\n  - name: dt\n    type: date\n  - name: amt\n    type: decimal\n"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn deletion_against_modification_conflicts() {
+    let outcome = merge("a: 1\nb: 2\n", "a: 1\n", "a: 1\nb: 3\n");
+    assert!(matches!(outcome, MergeOutcome::Conflicted(conflicts) if conflicts[0].path == "b"));
+}
@@ -0,0 +1,105 @@
+use yars_yaml_formatter::{format_yaml_range, format_yaml_string, YamlFormatError};
+
+const DOCUMENT: &str = "\
+table: orders   # hand-maintained
+validations:
+  - type: not_null
+    column: id
+  - type: range
+    column: amount
+    bounds: {max: 10, min: 0}
+columns: [id, amount]
+";
+
+fn range_of(haystack: &str, needle: &str) -> std::ops::Range<usize> {
+    let start = haystack.find(needle).expect("needle present");
+    start..start + needle.len()
+}
+
+#[test]
+fn only_the_enclosing_sequence_item_changes() {
+    let formatted = format_yaml_range(DOCUMENT, range_of(DOCUMENT, "- type: range")).unwrap();
+    assert_eq!(
+        formatted,
+        "\
+table: orders   # hand-maintained
+validations:
+  - type: not_null
+    column: id
+  - bounds:
+      max: 10
+      min: 0
+    column: amount
+    type: range
+columns: [id, amount]
+"
+    );
+}
+
+#[test]
+fn cursor_in_nested_value_formats_innermost_entry() {
+    let cursor = DOCUMENT.find("max").unwrap();
+    let formatted = format_yaml_range(DOCUMENT, cursor..cursor).unwrap();
+    assert_eq!(
+        formatted,
+        DOCUMENT.replace(
+            "    bounds: {max: 10, min: 0}\n",
+            "    bounds:\n      max: 10\n      min: 0\n"
+        )
+    );
+}
+
+#[test]
+fn whole_line_selection_selects_the_line_node() {
+    let line = range_of(DOCUMENT, "columns: [id, amount]\n");
+    let formatted = format_yaml_range(DOCUMENT, line).unwrap();
+    assert_eq!(
+        formatted,
+        DOCUMENT.replace("columns: [id, amount]\n", "columns:\n  - id\n  - amount\n")
+    );
+}
+
+#[test]
+fn selection_across_top_level_keys_formats_document() {
+    let formatted = format_yaml_range(DOCUMENT, 0..DOCUMENT.len()).unwrap();
+    assert_eq!(formatted, format_yaml_string(DOCUMENT).unwrap());
+}
+
+#[test]
+fn block_scalar_content_is_not_mistaken_for_keys() {
+    let input = "notes: |\n  step: one\n  step two\nz: 1\n";
+    let cursor = input.find("step: one").unwrap();
+    let formatted = format_yaml_range(input, cursor..cursor).unwrap();
+    assert_eq!(formatted, "notes: \"step: one\\nstep two\\n\"\nz: 1\n");
+}
+
+#[test]
+fn invalid_documents_are_rejected() {
+    let err = format_yaml_range("a: [1\n", 0..1).unwrap_err();
+    assert!(matches!(err, YamlFormatError::Parse { .. }));
+}
+
+#[test]
+fn nodes_that_cannot_stand_alone_are_refused() {
+    // The alias cannot be resolved once the node is cut out of the document.
+    let input = "base: &b 1\nother:\n  y: 2\n  x: *b\n";
+    let cursor = input.find("x:").unwrap();
+    let err = format_yaml_range(input, cursor..cursor).unwrap_err();
+    assert!(matches!(err, YamlFormatError::Format(_)));
+    assert_eq!(err.to_string(), "Error formatting YAML: cannot reformat the node at line 4 in isolation");
+}
+
+#[test]
+fn tags_inside_the_node_keep_their_handles() {
+    let input = "%TAG !e! tag:example.com,2000:\n---\nother:\n  y: !e!widget 2\n  x: 1\nfirst: 0\n";
+    let cursor = input.find("y:").unwrap();
+    assert_eq!(
+        format_yaml_range(input, cursor..cursor).unwrap(),
+        "%TAG !e! tag:example.com,2000:\n---\nother:\n  y: !e!widget 2\n  x: 1\nfirst: 0\n"
+    );
+    let cursor = input.find("other:").unwrap();
+    assert_eq!(
+        format_yaml_range(input, cursor..cursor).unwrap(),
+        "%TAG !e! tag:example.com,2000:\n---\nother:\n  x: 1\n  y: !e!widget 2\nfirst: 0\n"
+    );
+}
@@ -0,0 +1,72 @@
+use yars_yaml_formatter::{format_yaml_string, format_yaml_string_with_options, DocumentMarkers, FormatOptions};
+
+fn assert_stable(formatted: &str) {
+    assert_eq!(format_yaml_string(formatted).unwrap(), formatted);
+}
+
+fn preserve() -> FormatOptions {
+    FormatOptions {
+        document_markers: DocumentMarkers::Preserve,
+        ..FormatOptions::default()
+    }
+}
+
+#[test]
+fn tag_directives_and_shorthands_are_kept() {
+    let input = "%YAML 1.2\n%TAG !e! tag:example.com,2000:app/\n---\n\
+b: !e!widget bar\n\
+a: !<tag:example.com,2000:app/gadget> 1\n\
+c: !<tag:other.org,2001:thing> z\n";
+
+    let formatted = format_yaml_string_with_options(input, &preserve()).unwrap();
+    assert_eq!(
+        formatted,
+        "%YAML 1.2\n%TAG !e! tag:example.com,2000:app/\n---\n\
+a: !e!gadget 1\n\
+b: !e!widget bar\n\
+c: !<tag:other.org,2001:thing> z\n"
+    );
+    assert_eq!(format_yaml_string_with_options(&formatted, &preserve()).unwrap(), formatted);
+}
+
+#[test]
+fn tag_directives_in_use_survive_default_options() {
+    let input = "%TAG !e! tag:example.com,2000:\n---\na: !e!gadget 1\nb: !e!widget x\n";
+    assert_eq!(format_yaml_string_with_options(input, &FormatOptions::default()).unwrap(), input);
+
+    // Without tags using its handle, the directive goes with the markers.
+    let unused = "%YAML 1.2\n%TAG !e! tag:example.com,2000:\n---\nb: !local x\na: 1\n";
+    assert_eq!(format_yaml_string(unused).unwrap(), "a: 1\nb: !local x\n");
+}
+
+#[test]
+fn core_tags_are_kept() {
+    let input = "a: !!str 1\nb: !!int \"2\"\nc: !<tag:yaml.org,2002:str> x\nd: !!binary aGk=\ne: !local y\n";
+
+    let formatted = format_yaml_string(input).unwrap();
+    assert_eq!(
+        formatted,
+        "a: !!str 1\nb: !!int \"2\"\nc: !!str x\nd: !!binary \"aGk=\"\ne: !local y\n"
+    );
+    assert_stable(&formatted);
+}
+
+#[test]
+fn tagged_collections_use_block_style() {
+    let input = "a: !set {x: null}\nb:\n- !point [1, 2]\n";
+
+    let formatted = format_yaml_string(input).unwrap();
+    assert_eq!(formatted, "a: !set\n  x: null\nb:\n  - !point\n    - 1\n    - 2\n");
+    assert_stable(&formatted);
+}
+
+#[test]
+fn exclamation_marks_inside_scalars_are_not_tags() {
+    let input = "%TAG !e! tag:example.com,2000:\n---\na: \"!e!x\"\nb: hi !e!x\nc: |\n  !e!x\nd: !e!x y # !e!z\n";
+
+    let formatted = format_yaml_string_with_options(input, &preserve()).unwrap();
+    assert_eq!(
+        formatted,
+        "%TAG !e! tag:example.com,2000:\n---\na: \"!e!x\"\nb: \"hi !e!x\"\nc: \"!e!x\\n\"\nd: !e!x y\n"
+    );
+}
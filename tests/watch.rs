@@ -0,0 +1,73 @@
+use std::fs;
+use std::time::Duration;
+
+use tempfile::tempdir;
+use yars_yaml_formatter::watch::{yaml_files, Watcher};
+
+const DEBOUNCE: Duration = Duration::from_millis(50);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn saved_yaml_files_are_batched() {
+    let dir = tempdir().unwrap();
+    let mut watcher = Watcher::new(&[dir.path().to_path_buf()], DEBOUNCE).unwrap();
+
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+    fs::write(&spec, "b: 1\na: 2\n").unwrap();
+    fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+    let batch = watcher.next_batch(Some(TIMEOUT)).unwrap();
+    assert_eq!(batch, vec![std::path::absolute(&spec).unwrap()]);
+}
+
+#[test]
+fn saves_under_hidden_directories_are_ignored() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join(".cache")).unwrap();
+    let mut watcher = Watcher::new(&[dir.path().to_path_buf()], DEBOUNCE).unwrap();
+
+    fs::write(dir.path().join(".cache/state.yaml"), "b: 1\n").unwrap();
+    fs::write(dir.path().join(".hidden.yaml"), "b: 1\n").unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "b: 1\n").unwrap();
+
+    let batch = watcher.next_batch(Some(TIMEOUT)).unwrap();
+    assert_eq!(batch, vec![std::path::absolute(&spec).unwrap()]);
+}
+
+#[test]
+fn settled_content_is_not_reported_again() {
+    let dir = tempdir().unwrap();
+    let spec = dir.path().join("spec.yaml");
+    fs::write(&spec, "a: 1\n").unwrap();
+    let mut watcher = Watcher::new(std::slice::from_ref(&spec), DEBOUNCE).unwrap();
+
+    // Rewriting settled content, as the formatter itself does, is ignored.
+    watcher.settle(&spec);
+    fs::write(&spec, "a: 1\n").unwrap();
+    assert!(watcher.next_batch(Some(Duration::from_millis(500))).unwrap().is_empty());
+
+    fs::write(&spec, "a: 2\n").unwrap();
+    assert_eq!(watcher.next_batch(Some(TIMEOUT)).unwrap().len(), 1);
+}
+
+#[test]
+fn directories_expand_to_visible_yaml_files() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("specs/bronze")).unwrap();
+    fs::create_dir_all(dir.path().join(".git")).unwrap();
+    fs::write(dir.path().join("specs/bronze/a.yml"), "a: 1\n").unwrap();
+    fs::write(dir.path().join("specs/b.yaml"), "b: 1\n").unwrap();
+    fs::write(dir.path().join("specs/readme.md"), "").unwrap();
+    fs::write(dir.path().join(".git/config.yaml"), "").unwrap();
+
+    let files = yaml_files(&[dir.path().to_path_buf()]);
+    assert_eq!(
+        files,
+        vec![
+            dir.path().join("specs/b.yaml"),
+            dir.path().join("specs/bronze/a.yml"),
+        ]
+    );
+}